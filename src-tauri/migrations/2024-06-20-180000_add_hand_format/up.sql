@@ -0,0 +1,2 @@
+-- cash or zoom, zoom hands are played on a pool instead of a fixed table
+ALTER TABLE hand ADD COLUMN format TEXT NOT NULL DEFAULT 'cash';
@@ -0,0 +1,2 @@
+-- offset in seconds of the local time of the player, time stays in UTC
+ALTER TABLE hand ADD COLUMN local_offset INTEGER;
@@ -0,0 +1,22 @@
+-- USD, EUR, GBP, CAD, INR or PLAY for play money
+ALTER TABLE hand ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+-- the hands already imported take the currency of the limits in their header,
+-- the code after the limits or else the symbol, play money has neither.
+-- real_money wasn't filled before, the stats of the players are computed again
+-- with the rebuild-stats command
+UPDATE hand SET currency = (
+  SELECT CASE
+    WHEN header LIKE '% USD)%' THEN 'USD'
+    WHEN header LIKE '% EUR)%' THEN 'EUR'
+    WHEN header LIKE '% GBP)%' THEN 'GBP'
+    WHEN header LIKE '% CAD)%' THEN 'CAD'
+    WHEN header LIKE '% INR)%' THEN 'INR'
+    WHEN header LIKE '%($%' THEN 'USD'
+    WHEN header LIKE '%€/%' OR header LIKE '%(€%' THEN 'EUR'
+    WHEN header LIKE '%(£%' THEN 'GBP'
+    WHEN header LIKE '%(₹%' THEN 'INR'
+    ELSE 'PLAY'
+  END
+  FROM (SELECT substr(content, 1, instr(content || char(10), char(10)) - 1) AS header)
+);
+UPDATE hand SET real_money = currency <> 'PLAY';
@@ -0,0 +1,2 @@
+-- en, fr, de, es, pt or it
+ALTER TABLE hand ADD COLUMN language TEXT NOT NULL DEFAULT 'en';
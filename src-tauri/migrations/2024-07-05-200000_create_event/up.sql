@@ -0,0 +1,12 @@
+CREATE TABLE event(
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  player TEXT NOT NULL, -- may not be seated, "joins the table"
+  hand BIGINT NOT NULL REFERENCES hand(id),
+  kind TEXT NOT NULL, -- sit_out, timed_out, disconnected, join, chat, cash_out, ...
+  moment TEXT NOT NULL, -- start, preflop, flop, ...
+  sequence INTEGER NOT NULL, -- order of the events in the hand
+  seat INTEGER, -- join
+  amount FLOAT, -- cash_out
+  message TEXT, -- chat
+  UNIQUE(hand, sequence)
+);
@@ -0,0 +1,8 @@
+ALTER TABLE player DROP COLUMN nb_can_wsd;
+ALTER TABLE player DROP COLUMN nb_can_wtsd;
+ALTER TABLE player DROP COLUMN wsd;
+ALTER TABLE player DROP COLUMN wtsd;
+
+DROP TABLE winner;
+
+DROP TABLE board;
@@ -0,0 +1,26 @@
+-- boards of the hand, two when the hand is run twice
+CREATE TABLE board(
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  hand BIGINT NOT NULL REFERENCES hand(id),
+  run INTEGER NOT NULL, -- 0, 1 for the second board
+  card1 TEXT NOT NULL, -- empty when not dealt
+  card2 TEXT NOT NULL,
+  card3 TEXT NOT NULL,
+  card4 TEXT NOT NULL,
+  card5 TEXT NOT NULL,
+  UNIQUE(hand, run)
+);
+
+-- pots collected on each board, a board can be split between players
+CREATE TABLE winner(
+  id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  hand BIGINT NOT NULL REFERENCES hand(id),
+  run INTEGER NOT NULL,
+  player TEXT NOT NULL REFERENCES player(name),
+  amount FLOAT NOT NULL
+);
+
+ALTER TABLE player ADD COLUMN wtsd FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE player ADD COLUMN wsd FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE player ADD COLUMN nb_can_wtsd FLOAT NOT NULL DEFAULT 0;
+ALTER TABLE player ADD COLUMN nb_can_wsd FLOAT NOT NULL DEFAULT 0;
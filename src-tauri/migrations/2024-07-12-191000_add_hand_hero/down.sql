@@ -0,0 +1 @@
+ALTER TABLE hand DROP COLUMN hero;
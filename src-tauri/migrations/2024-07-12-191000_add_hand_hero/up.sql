@@ -0,0 +1,9 @@
+-- player dealt in, the account that wrote the history
+ALTER TABLE hand ADD COLUMN hero TEXT;
+-- the hands already imported are English, the hero is the name of the
+-- "Dealt to name [Ah Kd]" line
+UPDATE hand SET hero = (
+  SELECT substr(dealt, 1, instr(dealt, ' [') - 1)
+  FROM (SELECT substr(content, instr(content, char(10) || 'Dealt to ') + 10) AS dealt)
+)
+WHERE instr(content, char(10) || 'Dealt to ') > 0;
@@ -0,0 +1,8 @@
+-- position of the stat panel of each seat in the HUD window, from 0 to 1
+CREATE TABLE hud_seat (
+  table_size INTEGER NOT NULL,
+  seat INTEGER NOT NULL,
+  x REAL NOT NULL,
+  y REAL NOT NULL,
+  PRIMARY KEY (table_size, seat)
+);
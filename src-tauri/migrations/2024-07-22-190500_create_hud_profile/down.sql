@@ -0,0 +1 @@
+DROP TABLE hud_profile;
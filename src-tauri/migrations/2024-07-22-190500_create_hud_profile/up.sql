@@ -0,0 +1,6 @@
+-- stats shown by the HUD, the profile itself is kept as JSON
+CREATE TABLE hud_profile (
+  name TEXT PRIMARY KEY NOT NULL,
+  content TEXT NOT NULL,
+  active BOOLEAN NOT NULL DEFAULT 0
+);
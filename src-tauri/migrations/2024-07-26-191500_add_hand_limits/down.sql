@@ -0,0 +1,3 @@
+DROP INDEX hand_big_limit;
+ALTER TABLE hand DROP COLUMN big_limit;
+ALTER TABLE hand DROP COLUMN small_limit;
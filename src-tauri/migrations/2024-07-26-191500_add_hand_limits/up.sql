@@ -0,0 +1,7 @@
+-- stakes of the table, the hands already imported take the blinds posted
+ALTER TABLE hand ADD COLUMN small_limit REAL NOT NULL DEFAULT 0;
+ALTER TABLE hand ADD COLUMN big_limit REAL NOT NULL DEFAULT 0;
+UPDATE hand SET
+  small_limit = COALESCE((SELECT MAX(amount) FROM blind WHERE blind.hand = hand.id AND blind.kind = 'small'), 0),
+  big_limit = COALESCE((SELECT MAX(amount) FROM blind WHERE blind.hand = hand.id AND blind.kind = 'big'), 0);
+CREATE INDEX hand_big_limit ON hand (big_limit);
@@ -0,0 +1,3 @@
+-- big blinds won per 100 hands, rebuild-stats computes it for the hands
+-- already imported
+ALTER TABLE player ADD COLUMN winnings FLOAT NOT NULL DEFAULT 0;
@@ -0,0 +1,597 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use diesel::SqliteConnection;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::{self, DBError};
+use crate::hud_profile::{Stat, StatConfig};
+use crate::import;
+use crate::parse::{self, ParseError};
+use crate::range::{Card, Range};
+use crate::registry::TableRegistry;
+use crate::settings::{Settings, SettingsError};
+use crate::track::{Change, EventSink, LiveEvent, LiveImport, Tracker};
+
+// Commands run without the window, on a headless box or from a script.
+// The app starts the window when it's given no argument.
+
+const USAGE: &str = "Usage: inspector [--database FILE] <command>
+
+Commands:
+  import <path>... [--quarantine FILE]
+                                import the history files or folders, the
+                                rejected hands appended to the quarantine,
+                                \"-\" reads the histories from the input
+  watch [<path>...]             import the files as they're written, the
+                                history folders of the settings by default
+  stats <player>                stats of the player
+  hands [--player NAME] [--since DATE] [--limit N]
+                                hands the most recent first, DATE as
+                                2024-03-26 or \"2024-03-26 21:30\"
+  rebuild-stats                 compute the stats again from the hands
+  export hands|players [--player NAME] [--output FILE]
+                                hand histories as text or players as JSON
+  range <range> [--dead CARDS]  grid of a range as \"QQ+, AKs, 76s-54s\",
+                                without the known cards as \"Ah 7c\"
+
+Options:
+  --play-money                  play money stats and hands instead of real money
+  --json                        print JSON instead of text
+";
+
+const HANDS_LIMIT: i64 = 50;
+
+#[derive(Debug)]
+pub enum CliErrorType {
+  Usage,
+  Database,
+  Parse,
+  Io,
+  Tracking,
+  Settings,
+}
+
+#[derive(Debug)]
+pub struct CliError {
+  t: CliErrorType,
+  msg: String,
+}
+
+impl CliError {
+  pub fn err(t: CliErrorType, e: impl std::string::ToString) -> Self {
+    CliError {
+      t,
+      msg: e.to_string(),
+    }
+  }
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.t {
+      CliErrorType::Usage => write!(f, "{}\n\n{}", self.msg, USAGE),
+      _ => write!(f, "{:?} error : {}", self.t, self.msg),
+    }
+  }
+}
+
+impl From<DBError> for CliError {
+  fn from(e: DBError) -> Self {
+    CliError::err(CliErrorType::Database, e)
+  }
+}
+
+impl From<ParseError> for CliError {
+  fn from(e: ParseError) -> Self {
+    CliError::err(CliErrorType::Parse, e)
+  }
+}
+
+impl From<std::io::Error> for CliError {
+  fn from(e: std::io::Error) -> Self {
+    CliError::err(CliErrorType::Io, e)
+  }
+}
+
+impl From<SettingsError> for CliError {
+  fn from(e: SettingsError) -> Self {
+    CliError::err(CliErrorType::Settings, e)
+  }
+}
+
+impl From<notify::Error> for CliError {
+  fn from(e: notify::Error) -> Self {
+    CliError::err(CliErrorType::Tracking, e)
+  }
+}
+
+/// Arguments of a command, the options taking a value and the flags apart
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+  pub command: String,
+  pub values: Vec<String>,
+  pub options: Vec<(String, String)>,
+  pub flags: Vec<String>,
+}
+
+const FLAGS: [&str; 3] = ["--play-money", "--json", "--help"];
+const OPTIONS: [&str; 7] = [
+  "--database",
+  "--player",
+  "--since",
+  "--limit",
+  "--output",
+  "--dead",
+  "--quarantine",
+];
+
+impl Args {
+  pub fn parse(args: &[String]) -> Result<Self, CliError> {
+    let mut parsed = Args::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+      // --option=value or --option value
+      let (name, value) = match arg.split_once('=') {
+        Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+        _ => (arg.as_str(), None),
+      };
+      if FLAGS.contains(&name) && value.is_none() {
+        parsed.flags.push(name.to_string());
+      } else if OPTIONS.contains(&name) {
+        let value = match value.or_else(|| args.next().cloned()) {
+          Some(value) => value,
+          None => return Err(usage(format!("{} needs a value", name))),
+        };
+        parsed.options.push((name.to_string(), value));
+      } else if name.starts_with("--") {
+        return Err(usage(format!("unknown option {}", arg)));
+      } else if parsed.command.is_empty() {
+        parsed.command = arg.clone();
+      } else {
+        parsed.values.push(arg.clone());
+      }
+    }
+    Ok(parsed)
+  }
+
+  /// Last value given to the option
+  pub fn option(&self, name: &str) -> Option<&str> {
+    self
+      .options
+      .iter()
+      .rev()
+      .find(|(option, _)| option == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  pub fn flag(&self, name: &str) -> bool {
+    self.flags.iter().any(|flag| flag == name)
+  }
+}
+
+/// Run the command and return the exit code
+pub fn main(args: &[String], settings: Result<Settings, SettingsError>) -> i32 {
+  match run(args, settings) {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("{}", e);
+      match e.t {
+        CliErrorType::Usage => 2,
+        _ => 1,
+      }
+    }
+  }
+}
+
+pub fn run(args: &[String], settings: Result<Settings, SettingsError>) -> Result<(), CliError> {
+  let args = Args::parse(args)?;
+  if args.flag("--help") || args.command == "help" {
+    print!("{}", USAGE);
+    return Ok(());
+  }
+  // no database needed
+  if args.command == "range" {
+    return range(&args);
+  }
+  let mut settings = settings?;
+  if let Some(database) = args.option("--database") {
+    settings.database = database.to_string();
+  }
+  // an unknown time zone would print the hands at the local time
+  settings.validate()?;
+  let conn = &mut db::connect(&settings.database)?;
+  let real_money = !args.flag("--play-money");
+
+  match args.command.as_str() {
+    "import" if args.values == ["-"] => import_reader(conn, std::io::stdin().lock()),
+    "import" => import_paths(
+      conn,
+      &paths(&args, &settings)?,
+      args.option("--quarantine").map(Path::new),
+    ),
+    "watch" => watch(&settings, &paths(&args, &settings)?),
+    "stats" => match args.values.as_slice() {
+      [name] => stats(conn, name, real_money, args.flag("--json")),
+      _ => Err(usage("stats takes a player name")),
+    },
+    "hands" => {
+      let since = match args.option("--since") {
+        Some(date) => Some(
+          parse_date(date, time_zone(&settings))
+            .ok_or_else(|| usage(format!("{} is not a date", date)))?,
+        ),
+        None => None,
+      };
+      let limit = match args.option("--limit") {
+        Some(limit) => limit
+          .parse()
+          .map_err(|_| usage(format!("{} is not a number", limit)))?,
+        None => HANDS_LIMIT,
+      };
+      let hands = db::search_hands(
+        conn,
+        args.option("--player"),
+        Some(real_money),
+        since,
+        limit,
+        0,
+      )?;
+      print_hands(&hands, time_zone(&settings), args.flag("--json"))
+    }
+    "rebuild-stats" => {
+      let hands = db::rebuild_players(conn, |read| eprint!("\r{} hands", read))?;
+      eprintln!("\rstats of {} hands rebuilt", hands);
+      Ok(())
+    }
+    "export" => {
+      // the output file is only created for a known export
+      let what = match args.values.as_slice() {
+        [what] if what == "hands" || what == "players" => what.as_str(),
+        _ => return Err(usage("export takes hands or players")),
+      };
+      let mut output: Box<dyn Write> = match args.option("--output") {
+        Some(file) => Box::new(std::fs::File::create(file)?),
+        None => Box::new(std::io::stdout().lock()),
+      };
+      if what == "hands" {
+        export_hands(conn, args.option("--player"), real_money, &mut output)
+      } else {
+        let players = db::get_all_players(conn, real_money)?;
+        serde_json::to_writer_pretty(&mut output, &players)
+          .map_err(|e| CliError::err(CliErrorType::Io, e))?;
+        writeln!(output)?;
+        Ok(())
+      }
+    }
+    "" => Err(usage("no command")),
+    command => Err(usage(format!("unknown command {}", command))),
+  }
+}
+
+fn usage(msg: impl std::string::ToString) -> CliError {
+  CliError::err(CliErrorType::Usage, msg)
+}
+
+// the paths given, the history folders of the settings otherwise
+fn paths(args: &Args, settings: &Settings) -> Result<Vec<PathBuf>, CliError> {
+  let paths: Vec<PathBuf> = if args.values.is_empty() {
+    settings.available_dirs()
+  } else {
+    args.values.iter().map(PathBuf::from).collect()
+  };
+  if paths.is_empty() {
+    return Err(usage("no path given and no history folder in the settings"));
+  }
+  if let Some(path) = paths.iter().find(|path| !path.exists()) {
+    return Err(CliError::err(
+      CliErrorType::Io,
+      format!("{:?} doesn't exist", path),
+    ));
+  }
+  Ok(paths)
+}
+
+// "zcat hands.gz | inspector import -", a broken hand stops the import
+fn import_reader(conn: &mut SqliteConnection, reader: impl BufRead) -> Result<(), CliError> {
+  let hands = parse::parse_reader(reader)?;
+  let inserted = db::insert_new_hands(conn, &hands)?;
+  for error in &inserted.errors {
+    eprintln!("{}", error);
+  }
+  eprintln!("hands {} ({} new)", hands.len(), inserted.hands.len());
+  Ok(())
+}
+
+fn import_paths(
+  conn: &mut SqliteConnection,
+  paths: &[PathBuf],
+  quarantine: Option<&Path>,
+) -> Result<(), CliError> {
+  let report = import::import(paths, conn, |progress| eprint!("\r{}", progress));
+  eprintln!("\r{}", report.progress);
+  for diagnostic in &report.diagnostics {
+    eprintln!("{}", diagnostic);
+  }
+  if let Some(quarantine) = quarantine {
+    parse::write_quarantine(quarantine, &report.diagnostics)?;
+  }
+  for error in &report.file_errors {
+    eprintln!("{}", error);
+  }
+  match report.db_errors.into_iter().next() {
+    Some(e) => Err(e.into()),
+    None => Ok(()),
+  }
+}
+
+// each live event as a JSON line
+struct Print;
+
+impl EventSink for Print {
+  fn emit(&self, event: &LiveEvent) {
+    match serde_json::to_string(event) {
+      Ok(json) => println!("{}", json),
+      Err(e) => log::error!("Can't print {} : {}", event.name(), e),
+    }
+  }
+}
+
+fn watch(settings: &Settings, paths: &[PathBuf]) -> Result<(), CliError> {
+  let registry = Arc::new(Mutex::new(TableRegistry::new(Duration::from_secs(300))));
+  let mut live = LiveImport::new(db::connect(&settings.database)?, Print, registry);
+  let _tracker = Tracker::start(
+    paths,
+    &settings.watch_options(),
+    move |change| match change {
+      Change::Written(file) => live.import_file(&file),
+      Change::Removed(file) => log::info!("{:?} removed", file),
+    },
+  )?;
+  eprintln!("Watching {:?}, Ctrl-C to stop", paths);
+  loop {
+    std::thread::park();
+  }
+}
+
+fn range(args: &Args) -> Result<(), CliError> {
+  let range = match args.values.as_slice() {
+    [range] => Range::parse(range).map_err(usage)?,
+    _ => return Err(usage("range takes a range, quoted")),
+  };
+  let dead = args
+    .option("--dead")
+    .unwrap_or_default()
+    .split_whitespace()
+    .map(|card| Card::parse(card).ok_or_else(|| usage(format!("no card {}", card))))
+    .collect::<Result<Vec<Card>, CliError>>()?;
+  if args.flag("--json") {
+    let json = serde_json::to_string(&range.matrix(&dead))
+      .map_err(|e| CliError::err(CliErrorType::Io, e))?;
+    println!("{}", json);
+  } else {
+    println!("{}", range.matrix(&dead));
+  }
+  Ok(())
+}
+
+fn stats(
+  conn: &mut SqliteConnection,
+  name: &str,
+  real_money: bool,
+  json: bool,
+) -> Result<(), CliError> {
+  let player = db::get_player(conn, name, real_money)?
+    .ok_or_else(|| CliError::err(CliErrorType::Database, format!("no player {}", name)))?;
+  if json {
+    let json =
+      serde_json::to_string_pretty(&player).map_err(|e| CliError::err(CliErrorType::Io, e))?;
+    println!("{}", json);
+    return Ok(());
+  }
+  println!("{}", player.name);
+  for stat in [
+    Stat::Hands,
+    Stat::Vpip,
+    Stat::Pfr,
+    Stat::Af,
+    Stat::Pre3bet,
+    Stat::FoldPre3bet,
+    Stat::Cbet,
+    Stat::FoldCbet,
+    Stat::Squeeze,
+    Stat::Wtsd,
+    Stat::Wsd,
+  ] {
+    let value = StatConfig {
+      decimals: if stat == Stat::Hands { 0 } else { 1 },
+      ..StatConfig::new(stat)
+    }
+    .render(Some(&player));
+    println!(
+      "  {:<6} {:>7}  ({} occasions)",
+      value.label,
+      value.text,
+      stat.sample(&player)
+    );
+  }
+  Ok(())
+}
+
+fn print_hands(
+  hands: &[db::models::Hand],
+  time_zone: Option<Tz>,
+  json: bool,
+) -> Result<(), CliError> {
+  for hand in hands {
+    if json {
+      let json = serde_json::json!({
+        "id": hand.id,
+        "time": hand.time,
+        "table_name": hand.table_name,
+        "format": hand.format,
+        "currency": hand.currency,
+        "pot": hand.pot,
+        "winner": hand.winner,
+        "hero": hand.hero,
+      });
+      println!("{}", json);
+    } else {
+      println!(
+        "{}  {}  {:<20} {:>8.2} {}  won by {}",
+        hand.id,
+        format_time(hand.time, time_zone),
+        hand.table_name,
+        hand.pot,
+        hand.currency,
+        hand.winner
+      );
+    }
+  }
+  Ok(())
+}
+
+// the histories as the client writes them, so other trackers can read them
+fn export_hands(
+  conn: &mut SqliteConnection,
+  player: Option<&str>,
+  real_money: bool,
+  output: &mut impl Write,
+) -> Result<(), CliError> {
+  const BATCH_SIZE: i64 = 1000;
+  // the hands imported meanwhile don't move the batches
+  let mut last = 0;
+  loop {
+    let hands = db::hands_after(conn, player, real_money, last, BATCH_SIZE)?;
+    match hands.last() {
+      Some(hand) => last = hand.id,
+      None => return Ok(()),
+    }
+    for hand in hands {
+      write!(output, "{}\n\n\n", hand.content.trim_end())?;
+    }
+  }
+}
+
+// time zone of the settings, the one of the system when none, checked by
+// `Settings::validate` when the command starts
+fn time_zone(settings: &Settings) -> Option<Tz> {
+  settings
+    .time_zone
+    .as_ref()
+    .and_then(|time_zone| time_zone.parse().ok())
+}
+
+/// UTC timestamp of a date of the time zone, "2024-03-26" or "2024-03-26 21:30"
+pub fn parse_date(date: &str, time_zone: Option<Tz>) -> Option<i64> {
+  let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M")
+    .ok()
+    .or_else(|| {
+      NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })?;
+  let time = match time_zone {
+    Some(time_zone) => time_zone
+      .from_local_datetime(&naive)
+      .earliest()?
+      .timestamp(),
+    None => Local.from_local_datetime(&naive).earliest()?.timestamp(),
+  };
+  Some(time)
+}
+
+fn format_time(time: i64, time_zone: Option<Tz>) -> String {
+  const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+  match time_zone {
+    Some(time_zone) => time_zone
+      .timestamp_opt(time, 0)
+      .single()
+      .map(|time| time.format(FORMAT).to_string()),
+    None => Local
+      .timestamp_opt(time, 0)
+      .single()
+      .map(|time| time.format(FORMAT).to_string()),
+  }
+  .unwrap_or_else(|| time.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  #[test]
+  fn parse_args() {
+    let parsed = Args::parse(&args(&[
+      "hands",
+      "--player",
+      "sidneivl",
+      "--since=2024-03-26",
+      "--play-money",
+    ]))
+    .unwrap();
+    assert_eq!(parsed.command, "hands");
+    assert_eq!(parsed.option("--player"), Some("sidneivl"));
+    assert_eq!(parsed.option("--since"), Some("2024-03-26"));
+    assert!(parsed.flag("--play-money"));
+    assert!(!parsed.flag("--json"));
+
+    let parsed = Args::parse(&args(&["import", "a.txt", "b"])).unwrap();
+    assert_eq!(parsed.values, vec!["a.txt", "b"]);
+
+    assert!(Args::parse(&args(&["stats", "--player"])).is_err());
+    assert!(Args::parse(&args(&["stats", "--players", "x"])).is_err());
+
+    let brussels: Tz = "Europe/Brussels".parse().unwrap();
+    // winter time, an hour ahead of UTC
+    assert_eq!(parse_date("2024-03-26", Some(brussels)), Some(1711407600));
+    assert_eq!(
+      parse_date("2024-03-26 21:30", Some(brussels)),
+      Some(1711485000)
+    );
+    assert_eq!(parse_date("26/03/2024", Some(brussels)), None);
+  }
+
+  #[test]
+  fn export() {
+    let history = include_bytes!("../test/test_hands.txt");
+    let hands = crate::parse::parse_bytes(history).unwrap();
+    let mut conn = db::connect(":memory:").unwrap();
+    import_reader(&mut conn, &history[..]).unwrap();
+
+    let mut output = Vec::new();
+    export_hands(&mut conn, None, true, &mut output).unwrap();
+    let exported = crate::parse::parse_str(&String::from_utf8(output).unwrap()).unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].id, hands[0].id);
+
+    let mut output = Vec::new();
+    export_hands(&mut conn, Some("nobody"), false, &mut output).unwrap();
+    assert!(output.is_empty());
+  }
+
+  #[test]
+  fn checks_before_running() {
+    let output = std::env::temp_dir().join("hand_tracker_export_check.json");
+    let _ = std::fs::remove_file(&output);
+    let command = args(&["export", "hand", "--database", ":memory:", "--output"]);
+    let command = [command, vec![output.to_string_lossy().to_string()]].concat();
+    let e = run(&command, Ok(Settings::default())).unwrap_err();
+    assert!(matches!(e.t, CliErrorType::Usage));
+    assert!(!output.exists());
+
+    let settings = Settings {
+      time_zone: Some("Europe/Brusels".to_string()),
+      ..Settings::default()
+    };
+    let e = run(&args(&["hands", "--database", ":memory:"]), Ok(settings)).unwrap_err();
+    assert!(matches!(e.t, CliErrorType::Settings));
+  }
+}
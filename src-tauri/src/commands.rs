@@ -0,0 +1,789 @@
+use diesel::SqliteConnection;
+use notify::RecommendedWatcher;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, WindowBuilder, WindowUrl};
+
+use crate::db::query::{self, Cursor, HandFilter};
+use crate::db::{self, models, DBError};
+use crate::discover::{self, HistoryFolder};
+use crate::hero::HeroReport;
+use crate::hud::{self, Hud, HudFrame, LiveTables, WindowHost};
+use crate::hud_profile::{self, HudProfile};
+use crate::import::{self, Progress};
+use crate::parse::{self, ParseError};
+use crate::range::{Card, Range, RangeMatrix};
+use crate::registry::{TableRegistry, TableSnapshot};
+use crate::replay::{self, HandReplay, Step};
+use crate::settings::{self, Settings, SettingsError};
+use crate::track::{Change, EventSink, LiveEvent, LiveImport, Throttle, Tracker};
+
+// Commands called by the frontend with `invoke`. Errors are sent as
+// `{ kind, message }` so the frontend can tell them apart.
+
+// hands returned by a search when no limit is given
+const SEARCH_LIMIT: i64 = 50;
+// hands shown in a player profile
+const RECENT_HANDS: i64 = 20;
+// live events are sent to the webview at most this often
+const EVENT_INTERVAL: Duration = Duration::from_millis(500);
+// a table without new hand for this long is closed
+const TABLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum CommandError {
+  Database(String),
+  Parse(String),
+  Tracking(String),
+  NotFound(String),
+  Invalid(String), // a value sent by the frontend that can't be used
+  Settings(String),
+  Busy(String), // an import is already running
+  Lock(String), // a thread panicked holding the state
+}
+
+impl From<DBError> for CommandError {
+  fn from(e: DBError) -> Self {
+    CommandError::Database(e.to_string())
+  }
+}
+
+impl From<ParseError> for CommandError {
+  fn from(e: ParseError) -> Self {
+    CommandError::Parse(e.to_string())
+  }
+}
+
+impl From<SettingsError> for CommandError {
+  fn from(e: SettingsError) -> Self {
+    CommandError::Settings(e.to_string())
+  }
+}
+
+impl From<notify::Error> for CommandError {
+  fn from(e: notify::Error) -> Self {
+    CommandError::Tracking(e.to_string())
+  }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, CommandError> {
+  mutex.lock().map_err(|e| CommandError::Lock(e.to_string()))
+}
+
+impl EventSink for AppHandle {
+  fn emit(&self, event: &LiveEvent) {
+    if let Err(e) = self.emit_all(event.name(), event) {
+      log::error!("Can't send {} : {}", event.name(), e);
+    }
+  }
+}
+
+// HUD windows over the tables, transparent and without decorations
+struct TauriHost<'a>(&'a AppHandle);
+
+impl WindowHost for TauriHost<'_> {
+  fn open(&mut self, label: &str, title: &str) -> Result<(), String> {
+    WindowBuilder::new(self.0, label, WindowUrl::App("hud".into()))
+      .title(title)
+      .inner_size(hud::WINDOW_WIDTH, hud::WINDOW_HEIGHT)
+      .transparent(true)
+      .decorations(false)
+      .always_on_top(true)
+      .skip_taskbar(true)
+      .resizable(false)
+      .build()
+      .map(|_| ())
+      .map_err(|e| e.to_string())
+  }
+
+  fn close(&mut self, label: &str) {
+    if let Some(window) = self.0.get_window(label) {
+      if let Err(e) = window.close() {
+        log::error!("Can't close {} : {}", label, e);
+      }
+    }
+  }
+
+  fn show(&mut self, label: &str, frame: &HudFrame) {
+    if let Err(e) = self.0.emit_to(label, "hud-frame", frame) {
+      log::error!("Can't send the HUD of {} : {}", label, e);
+    }
+  }
+
+  fn set_click_through(&mut self, label: &str, enabled: bool) {
+    if let Some(window) = self.0.get_window(label) {
+      if let Err(e) = window.set_ignore_cursor_events(enabled) {
+        log::error!("Can't set click through on {} : {}", label, e);
+      }
+    }
+  }
+}
+
+// Follow the registry with the HUD windows
+fn refresh(app: &AppHandle) -> Result<(), CommandError> {
+  let state = app.state::<AppState>();
+  let registry = lock(&state.tables)?;
+  let mut conn = lock(&state.conn)?;
+  let mut hud = lock(&state.hud)?;
+  let mut tables = LiveTables {
+    registry: &registry,
+    conn: &mut conn,
+  };
+  hud.refresh(&mut TauriHost(app), &mut tables)?;
+  Ok(())
+}
+
+pub struct AppState {
+  pub conn: Mutex<SqliteConnection>,
+  pub read: Mutex<SqliteConnection>, // long searches, not to hold the other commands
+  pub import: Arc<Mutex<ImportStatus>>,
+  pub tracker: Mutex<Option<Tracker>>,
+  pub tables: Arc<Mutex<TableRegistry>>,
+  pub hud: Mutex<Hud>,
+  pub settings: Mutex<Settings>,
+  pub settings_path: PathBuf,
+  settings_watcher: Mutex<Option<RecommendedWatcher>>,
+  replay: Mutex<Option<HandReplay>>, // last hand replayed, stepped through by the frontend
+}
+
+impl AppState {
+  pub fn new(
+    conn: SqliteConnection,
+    read: SqliteConnection,
+    settings: Settings,
+    settings_path: PathBuf,
+  ) -> Self {
+    AppState {
+      conn: Mutex::new(conn),
+      read: Mutex::new(read),
+      import: Arc::new(Mutex::new(ImportStatus::default())),
+      tracker: Mutex::new(None),
+      tables: Arc::new(Mutex::new(TableRegistry::new(TABLE_TIMEOUT))),
+      hud: Mutex::new(Hud::default()),
+      settings: Mutex::new(settings),
+      settings_path,
+      settings_watcher: Mutex::new(None),
+      replay: Mutex::new(None),
+    }
+  }
+
+  fn database(&self) -> Result<String, CommandError> {
+    Ok(lock(&self.settings)?.database.clone())
+  }
+}
+
+/// Start what the settings ask for once the app is running: the import of
+/// the history folders, the live tracking and the watch of the settings file
+pub fn setup(app: &AppHandle) {
+  let state = app.state::<AppState>();
+  let settings = match lock(&state.settings) {
+    Ok(settings) => settings.clone(),
+    Err(_) => return,
+  };
+  let history_dirs = settings.available_dirs();
+  if settings.auto_import && !history_dirs.is_empty() {
+    if let Err(e) = start_import(&state, history_dirs.clone()) {
+      log::error!("Import on startup failed : {:?}", e);
+    }
+  }
+  if settings.live_tracking {
+    if let Err(e) = start(app, &history_dirs) {
+      log::error!("Can't track {:?} : {:?}", history_dirs, e);
+    }
+  }
+
+  let watched = app.clone();
+  let watcher = settings::watch(&state.settings_path, move |settings| {
+    if let Err(e) = apply_settings(&watched, settings) {
+      log::error!("Can't apply the settings : {:?}", e);
+    }
+  });
+  match watcher {
+    Ok(watcher) => {
+      if let Ok(mut settings_watcher) = state.settings_watcher.lock() {
+        *settings_watcher = Some(watcher);
+      }
+    }
+    Err(e) => log::error!("Can't watch the settings : {}", e),
+  }
+}
+
+// Apply what changed in the settings and tell the frontend
+fn apply_settings(app: &AppHandle, settings: Settings) -> Result<(), CommandError> {
+  let state = app.state::<AppState>();
+  let old = lock(&state.settings)?.clone();
+  if old == settings {
+    return Ok(());
+  }
+  if old.database != settings.database {
+    *lock(&state.conn)? = db::connect(&settings.database)?;
+    *lock(&state.read)? = db::connect(&settings.database)?;
+  }
+  *lock(&state.settings)? = settings.clone();
+
+  let tracking = old.live_tracking != settings.live_tracking
+    || old.history_dirs != settings.history_dirs
+    || old.database != settings.database
+    || old.watch_mode != settings.watch_mode
+    || old.poll_interval_ms != settings.poll_interval_ms;
+  if tracking {
+    let tracker = lock(&state.tracker)?.take();
+    drop(tracker);
+    if settings.live_tracking {
+      start(app, &settings.available_dirs())?;
+    }
+  }
+  refresh(app)?;
+
+  if let Err(e) = app.emit_all("settings-changed", &settings) {
+    log::error!("Can't send settings-changed : {}", e);
+  }
+  Ok(())
+}
+
+/// Progress of the last folder imported, the files tracked are reported with
+/// live events
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct ImportStatus {
+  pub running: bool,
+  pub files_total: usize,
+  pub files_done: usize,
+  pub hands: usize,
+  pub inserted: usize,
+  pub errors: usize,
+  pub elapsed_ms: u128,
+  pub messages: Vec<String>, // files and batches that failed
+}
+
+impl ImportStatus {
+  fn update(&mut self, progress: &Progress) {
+    self.files_total = progress.files_total;
+    self.files_done = progress.files_done;
+    self.hands = progress.hands;
+    self.inserted = progress.inserted;
+    self.errors = progress.errors;
+    self.elapsed_ms = progress.elapsed.as_millis();
+  }
+}
+
+// Import the files with a connection of their own, the one of the state stays
+// free for the other commands
+fn run_import(
+  paths: &[PathBuf],
+  database: &str,
+  status: &Mutex<ImportStatus>,
+) -> Result<(), CommandError> {
+  let mut conn = db::connect(database)?;
+  let report = import::import(paths, &mut conn, |progress| {
+    if let Ok(mut status) = status.lock() {
+      status.update(progress);
+    }
+  });
+
+  let mut status = lock(status)?;
+  status.update(&report.progress);
+  status
+    .messages
+    .extend(report.file_errors.iter().map(ToString::to_string));
+  status
+    .messages
+    .extend(report.db_errors.iter().map(ToString::to_string));
+  Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct HandSummary {
+  pub id: i64,
+  pub time: i64,
+  pub table_name: String,
+  pub format: String,
+  pub currency: String,
+  pub pot: f32,
+  pub big_limit: f32,
+  pub winner: String,
+  pub hero: Option<String>,
+  pub players: Vec<String>,
+}
+
+impl From<models::Hand> for HandSummary {
+  fn from(hand: models::Hand) -> Self {
+    let players = [
+      hand.player1,
+      hand.player2,
+      hand.player3,
+      hand.player4,
+      hand.player5,
+      hand.player6,
+      hand.player7,
+      hand.player8,
+      hand.player9,
+    ];
+    HandSummary {
+      id: hand.id,
+      time: hand.time,
+      table_name: hand.table_name,
+      format: hand.format,
+      currency: hand.currency,
+      pot: hand.pot,
+      big_limit: hand.big_limit,
+      winner: hand.winner,
+      hero: hand.hero,
+      players: players.into_iter().filter(|p| !p.is_empty()).collect(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerProfile {
+  pub stats: models::Player,
+  pub hands: i64,
+  pub events: Vec<(String, i64)>, // number of table events of each kind
+  pub recent_hands: Vec<HandSummary>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HandSearch {
+  pub player: Option<String>,
+  pub real_money: Option<bool>,
+  pub since: Option<i64>, // UTC timestamp
+  pub limit: Option<i64>,
+  pub offset: Option<i64>,
+}
+
+// the replay is kept so the steps of the hand don't parse it again
+fn load_replay(state: &AppState, id: i64) -> Result<HandReplay, CommandError> {
+  if let Some(hand_replay) = lock(&state.replay)?.as_ref() {
+    if hand_replay.id == id {
+      return Ok(hand_replay.clone());
+    }
+  }
+  let hand = {
+    let mut conn = lock(&state.conn)?;
+    db::get_hand(&mut conn, id)?
+  };
+  let hand_detail = parse::parse_hand(&hand.content)?;
+  let hand_replay = replay::hand_replay(&hand_detail);
+  *lock(&state.replay)? = Some(hand_replay.clone());
+  Ok(hand_replay)
+}
+
+/// Replay of the hand, a step per action
+#[tauri::command]
+pub fn get_hand_replay(state: State<AppState>, id: i64) -> Result<HandReplay, CommandError> {
+  load_replay(&state, id)
+}
+
+/// Step of the replay at the action index
+#[tauri::command]
+pub fn get_replay_step(
+  state: State<AppState>,
+  id: i64,
+  index: usize,
+) -> Result<Step, CommandError> {
+  let hand_replay = load_replay(&state, id)?;
+  hand_replay
+    .steps
+    .get(index)
+    .cloned()
+    .ok_or(CommandError::NotFound(format!(
+      "no step {} in hand {}",
+      index, id
+    )))
+}
+
+/// Every player with their stats, the ones with the most hands first
+#[tauri::command]
+pub fn list_players(
+  state: State<AppState>,
+  real_money: bool,
+) -> Result<Vec<models::Player>, CommandError> {
+  let mut conn = lock(&state.conn)?;
+  Ok(db::get_all_players(&mut conn, real_money)?)
+}
+
+#[tauri::command]
+pub fn get_player_profile(
+  state: State<AppState>,
+  name: String,
+  real_money: bool,
+) -> Result<PlayerProfile, CommandError> {
+  let mut conn = lock(&state.conn)?;
+  let stats = db::get_player(&mut conn, &name, real_money)?
+    .ok_or(CommandError::NotFound(format!("no player {}", name)))?;
+  let hands = db::count_hands(&mut conn, &name)?;
+  let events = db::count_events(&mut conn, &name)?;
+  let recent_hands = db::search_hands(
+    &mut conn,
+    Some(&name),
+    Some(real_money),
+    None,
+    RECENT_HANDS,
+    0,
+  )?;
+  Ok(PlayerProfile {
+    stats,
+    hands,
+    events,
+    recent_hands: recent_hands.into_iter().map(HandSummary::from).collect(),
+  })
+}
+
+/// Hands of a player, or of everyone, the most recent first
+#[tauri::command]
+pub fn search_hands(
+  state: State<AppState>,
+  search: HandSearch,
+) -> Result<Vec<HandSummary>, CommandError> {
+  let mut conn = lock(&state.conn)?;
+  let hands = db::search_hands(
+    &mut conn,
+    search.player.as_deref(),
+    search.real_money,
+    search.since,
+    search.limit.unwrap_or(SEARCH_LIMIT),
+    search.offset.unwrap_or(0),
+  )?;
+  Ok(hands.into_iter().map(HandSummary::from).collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct HandPage {
+  pub hands: Vec<HandSummary>,
+  pub next: Option<Cursor>, // none once every hand was read
+}
+
+/// Hands passing the filters, the most recent first. The next page starts
+/// after `next`. The filters on the actions parse every hand, the search
+/// runs on the search connection not to hold the other commands.
+#[tauri::command]
+pub async fn query_hands(
+  state: State<'_, AppState>,
+  filter: HandFilter,
+  limit: Option<i64>,
+  after: Option<Cursor>,
+) -> Result<HandPage, CommandError> {
+  let mut conn = lock(&state.read)?;
+  let page = query::search(
+    &mut conn,
+    &filter,
+    limit.unwrap_or(SEARCH_LIMIT).max(1),
+    after,
+  )?;
+  Ok(HandPage {
+    hands: page.hands.into_iter().map(HandSummary::from).collect(),
+    next: page.next,
+  })
+}
+
+/// Start importing the folder in the background, follow it with
+/// `get_import_progress`
+#[tauri::command]
+pub fn import_folder(state: State<AppState>, path: String) -> Result<(), CommandError> {
+  let path = PathBuf::from(path);
+  if !path.exists() {
+    return Err(CommandError::NotFound(format!("no folder {:?}", path)));
+  }
+  start_import(&state, vec![path])
+}
+
+fn start_import(state: &AppState, paths: Vec<PathBuf>) -> Result<(), CommandError> {
+  let database = state.database()?;
+  {
+    let mut status = lock(&state.import)?;
+    if status.running {
+      return Err(CommandError::Busy(
+        "an import is already running".to_string(),
+      ));
+    }
+    *status = ImportStatus {
+      running: true,
+      ..Default::default()
+    };
+  }
+
+  let status = Arc::clone(&state.import);
+  std::thread::spawn(move || {
+    if let Err(e) = run_import(&paths, &database, &status) {
+      log::error!("Import failed : {:?}", e);
+      if let Ok(mut status) = status.lock() {
+        status.messages.push(format!("{:?}", e));
+      }
+    }
+    if let Ok(mut status) = status.lock() {
+      status.running = false;
+    }
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_import_progress(state: State<AppState>) -> Result<ImportStatus, CommandError> {
+  Ok(lock(&state.import)?.clone())
+}
+
+/// Import the history files of the folder, or of the folders of the
+/// settings, as the client writes them. The frontend listens to the
+/// `LiveEvent`s. The folders already tracked are replaced.
+#[tauri::command]
+pub async fn start_tracking(app: AppHandle, path: Option<String>) -> Result<(), CommandError> {
+  let paths = match path {
+    Some(path) => vec![PathBuf::from(path)],
+    None => lock(&app.state::<AppState>().settings)?.available_dirs(),
+  };
+  start(&app, &paths)
+}
+
+fn start(app: &AppHandle, paths: &[PathBuf]) -> Result<(), CommandError> {
+  if paths.is_empty() {
+    return Err(CommandError::Invalid(
+      "no history folder to track".to_string(),
+    ));
+  }
+  let state = app.state::<AppState>();
+  let sink = Throttle::new(app.clone(), EVENT_INTERVAL);
+  let registry = Arc::clone(&state.tables);
+  let mut live = LiveImport::new(db::connect(&state.database()?)?, sink, registry);
+  let options = lock(&state.settings)?.watch_options();
+  let app = app.clone();
+  let tracker = Tracker::start(paths, &options, move |change| {
+    match change {
+      Change::Written(file) => live.import_file(&file),
+      // the hands stay in the database, the table closes with the timeout
+      Change::Removed(file) => {
+        log::info!("{:?} removed", file);
+        return;
+      }
+    }
+    if let Err(e) = refresh(&app) {
+      log::error!("Can't refresh the HUD : {:?}", e);
+    }
+  })?;
+  // the old tracker waits for its thread, which may be refreshing the HUD,
+  // so it's dropped once the lock is released
+  let old = lock(&state.tracker)?.replace(tracker);
+  drop(old);
+  Ok(())
+}
+
+/// Stop the live import. Dropping the tracker joins its thread, which may be
+/// opening HUD windows on the main thread, so this doesn't run on it.
+#[tauri::command]
+pub async fn stop_tracking(app: AppHandle) -> Result<(), CommandError> {
+  let state = app.state::<AppState>();
+  let tracker = lock(&state.tracker)?.take();
+  drop(tracker);
+  // no table is followed anymore
+  lock(&state.hud)?.close_all(&mut TauriHost(&app));
+  Ok(())
+}
+
+/// Tables open in the client, each seat with the stats of its player, for
+/// the HUD
+#[tauri::command]
+pub fn get_tables(state: State<AppState>) -> Result<Vec<TableSnapshot>, CommandError> {
+  let tables = lock(&state.tables)?;
+  let mut conn = lock(&state.conn)?;
+  Ok(tables.snapshot(&mut conn, Instant::now())?)
+}
+
+/// Send the panels to the HUD windows again, called by a window once loaded.
+/// Windows can only be created outside of the main thread, so the HUD
+/// commands are async.
+#[tauri::command]
+pub async fn refresh_hud(app: AppHandle) -> Result<(), CommandError> {
+  refresh(&app)
+}
+
+#[tauri::command]
+pub async fn set_hud_click_through(app: AppHandle, enabled: bool) -> Result<(), CommandError> {
+  let state = app.state::<AppState>();
+  let mut hud = lock(&state.hud)?;
+  hud.set_click_through(&mut TauriHost(&app), enabled);
+  Ok(())
+}
+
+/// Position of the panels for the table size, the default one when never saved
+#[tauri::command]
+pub fn get_hud_layout(
+  state: State<AppState>,
+  table_size: u8,
+) -> Result<Vec<models::HudSeat>, CommandError> {
+  let mut conn = lock(&state.conn)?;
+  let seats = db::get_hud_seats(&mut conn, table_size as i32)?;
+  if seats.is_empty() {
+    return Ok(hud::default_layout(table_size));
+  }
+  Ok(seats)
+}
+
+#[tauri::command]
+pub async fn save_hud_layout(
+  app: AppHandle,
+  seats: Vec<models::HudSeat>,
+) -> Result<(), CommandError> {
+  {
+    let state = app.state::<AppState>();
+    let mut conn = lock(&state.conn)?;
+    db::save_hud_seats(&mut conn, &seats)?;
+  }
+  refresh(&app)
+}
+
+#[derive(Debug, Serialize)]
+pub struct HudProfiles {
+  pub active: String,
+  pub profiles: Vec<HudProfile>, // the default one when none is saved
+}
+
+#[tauri::command]
+pub fn list_hud_profiles(state: State<AppState>) -> Result<HudProfiles, CommandError> {
+  let mut conn = lock(&state.conn)?;
+  let mut profiles = db::get_hud_profiles(&mut conn)?
+    .iter()
+    .map(HudProfile::from_row)
+    .collect::<Result<Vec<HudProfile>, DBError>>()?;
+  let active = hud_profile::active(&mut conn)?;
+  if profiles.is_empty() {
+    profiles.push(active.clone());
+  }
+  Ok(HudProfiles {
+    active: active.name,
+    profiles,
+  })
+}
+
+/// Create the profile, or edit the one with the same name
+#[tauri::command]
+pub async fn save_hud_profile(app: AppHandle, profile: HudProfile) -> Result<(), CommandError> {
+  if profile.name.trim().is_empty() {
+    return Err(CommandError::Invalid(
+      "a HUD profile needs a name".to_string(),
+    ));
+  }
+  {
+    let state = app.state::<AppState>();
+    let mut conn = lock(&state.conn)?;
+    db::save_hud_profile(&mut conn, &profile.name, &profile.to_json()?)?;
+  }
+  refresh(&app)
+}
+
+#[tauri::command]
+pub async fn delete_hud_profile(app: AppHandle, name: String) -> Result<(), CommandError> {
+  {
+    let state = app.state::<AppState>();
+    let mut conn = lock(&state.conn)?;
+    if db::delete_hud_profile(&mut conn, &name)? == 0 {
+      return Err(CommandError::NotFound(format!("no HUD profile {}", name)));
+    }
+  }
+  refresh(&app)
+}
+
+/// Show the stats of the profile in the HUD
+#[tauri::command]
+pub async fn set_active_hud_profile(app: AppHandle, name: String) -> Result<(), CommandError> {
+  {
+    let state = app.state::<AppState>();
+    let mut conn = lock(&state.conn)?;
+    if !db::set_active_hud_profile(&mut conn, &name)? {
+      return Err(CommandError::NotFound(format!("no HUD profile {}", name)));
+    }
+  }
+  refresh(&app)
+}
+
+#[tauri::command]
+pub fn get_settings(state: State<AppState>) -> Result<Settings, CommandError> {
+  Ok(lock(&state.settings)?.clone())
+}
+
+/// Check and save the settings, then apply them
+#[tauri::command]
+pub async fn save_settings(app: AppHandle, settings: Settings) -> Result<(), CommandError> {
+  settings.validate()?;
+  settings.save(&app.state::<AppState>().settings_path)?;
+  apply_settings(&app, settings)
+}
+
+/// 13x13 grid of the range, without the combos using the known cards
+#[tauri::command]
+pub fn get_range_matrix(range: String, dead: Vec<String>) -> Result<RangeMatrix, CommandError> {
+  let range = Range::parse(&range).map_err(|e| CommandError::Invalid(e.to_string()))?;
+  let dead = dead
+    .iter()
+    .map(|card| Card::parse(card).ok_or(CommandError::Invalid(format!("no card {}", card))))
+    .collect::<Result<Vec<Card>, CommandError>>()?;
+  Ok(range.matrix(&dead))
+}
+
+/// Stats of each hero account of the settings and of all of them merged,
+/// read on the search connection not to hold the other commands. Without
+/// account in the settings, the ones that wrote the histories.
+#[tauri::command]
+pub async fn get_hero_report(
+  state: State<'_, AppState>,
+  real_money: bool,
+) -> Result<HeroReport, CommandError> {
+  const BATCH_SIZE: i64 = 1000;
+  let mut heroes = lock(&state.settings)?.heroes();
+  let mut conn = lock(&state.read)?;
+  if heroes.aliases.is_empty() {
+    heroes.aliases = db::get_heroes(&mut conn)?;
+  }
+  let mut report = HeroReport::new(&heroes, real_money);
+  let mut last = 0;
+  loop {
+    let hands = db::hero_hands_after(&mut conn, &heroes.aliases, real_money, last, BATCH_SIZE)?;
+    match hands.last() {
+      Some(hand) => last = hand.id,
+      None => return Ok(report),
+    }
+    let hands = hands
+      .iter()
+      .map(|hand| parse::parse_hand(&hand.content))
+      .collect::<Result<Vec<_>, _>>()?;
+    report.add(&hands, &heroes);
+  }
+}
+
+/// HandHistory folders of the PokerStars clients found on the system, Wine
+/// prefixes and Windows partitions included
+#[tauri::command]
+pub async fn discover_history_folders() -> Vec<HistoryFolder> {
+  discover::discover()
+}
+
+/// Add the folders and their accounts to the settings, so they're tracked
+/// with the others, and import them if asked
+#[tauri::command]
+pub async fn add_history_folders(
+  app: AppHandle,
+  folders: Vec<HistoryFolder>,
+  import: bool,
+) -> Result<(), CommandError> {
+  let state = app.state::<AppState>();
+  let mut settings = lock(&state.settings)?.clone();
+  for folder in &folders {
+    if !settings.history_dirs.contains(&folder.path) {
+      settings.history_dirs.push(folder.path.clone());
+    }
+    if !settings.heroes.contains(&folder.hero) {
+      settings.heroes.push(folder.hero.clone());
+    }
+  }
+  settings.validate()?;
+  settings.save(&state.settings_path)?;
+  apply_settings(&app, settings)?;
+
+  if import {
+    start_import(
+      &state,
+      folders.into_iter().map(|folder| folder.path).collect(),
+    )?;
+  }
+  Ok(())
+}
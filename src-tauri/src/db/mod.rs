@@ -1,15 +1,26 @@
 pub mod models;
+pub mod query;
+// generated by diesel, the table holeCard keeps its name
+#[allow(non_snake_case)]
 pub mod schema;
 
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
-use dotenvy::dotenv;
-use std::env;
+use diesel::sql_types::Bool;
+use diesel::sqlite::Sqlite;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parse::{self, HandDetail};
+use crate::stats;
 
 #[derive(Debug)]
 pub enum DBErrorType {
   Connection,
   Insert,
   Select,
+  Content, // a value stored that can't be read back
 }
 
 #[derive(Debug)]
@@ -25,13 +36,60 @@ impl DBError {
       msg: format!("Error : {}", e.to_string()),
     }
   }
+
+  // name the hand in the message
+  fn for_hand(self, hand_id: i64) -> Self {
+    DBError {
+      msg: format!("{} (hand {})", self.msg, hand_id),
+      ..self
+    }
+  }
 }
 
-pub fn establish_connection() -> Result<SqliteConnection, DBError> {
-  dotenv().ok();
+impl fmt::Display for DBError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?} {}", self.t, self.msg)
+  }
+}
 
-  let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-  SqliteConnection::establish(&database_url).map_err(|e| DBError::err(DBErrorType::Connection, e))
+impl From<diesel::result::Error> for DBError {
+  fn from(e: diesel::result::Error) -> Self {
+    use diesel::result::{DatabaseErrorKind, Error};
+    let t = match &e {
+      Error::NotFound | Error::QueryBuilderError(_) => DBErrorType::Select,
+      Error::DeserializationError(_) => DBErrorType::Content,
+      Error::SerializationError(_)
+      | Error::DatabaseError(
+        DatabaseErrorKind::UniqueViolation
+        | DatabaseErrorKind::ForeignKeyViolation
+        | DatabaseErrorKind::NotNullViolation
+        | DatabaseErrorKind::CheckViolation,
+        _,
+      ) => DBErrorType::Insert,
+      // locked database, failed commit or rollback
+      _ => DBErrorType::Connection,
+    };
+    DBError::err(t, e)
+  }
+}
+
+// migrations are built in the app, a new database is created on first run
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// Open the database and bring it to the last migration
+pub fn connect(database_url: &str) -> Result<SqliteConnection, DBError> {
+  let mut conn = SqliteConnection::establish(database_url)
+    .map_err(|e| DBError::err(DBErrorType::Connection, e))?;
+  // the import, the live tracking and the commands each hold a connection,
+  // wait for the lock instead of failing with "database is locked" and let
+  // the readers go on while a batch is written
+  conn
+    .batch_execute("PRAGMA busy_timeout = 5000; PRAGMA journal_mode = WAL;")
+    .map_err(|e| DBError::err(DBErrorType::Connection, e))?;
+  conn
+    .run_pending_migrations(MIGRATIONS)
+    .map_err(|e| DBError::err(DBErrorType::Connection, e))?;
+  Ok(conn)
 }
 
 pub fn insert_hand(
@@ -84,33 +142,501 @@ pub fn insert_hole_cards(
   Ok(())
 }
 
-pub fn show_hands(conn: &mut SqliteConnection) -> Result<(), DBError> {
-  use crate::db::models::Hand;
+pub fn insert_boards(
+  conn: &mut SqliteConnection,
+  boards: &Vec<models::Board>,
+  winners: &Vec<models::Winner>,
+) -> Result<(), DBError> {
+  diesel::insert_into(schema::board::table)
+    .values(boards)
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))?;
+  diesel::insert_into(schema::winner::table)
+    .values(winners)
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))?;
+  Ok(())
+}
+
+pub fn insert_events(
+  conn: &mut SqliteConnection,
+  events: &Vec<models::Event>,
+) -> Result<(), DBError> {
+  diesel::insert_into(schema::event::table)
+    .values(events)
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))?;
+  Ok(())
+}
+
+pub fn hand_exists(conn: &mut SqliteConnection, hand_id: i64) -> Result<bool, DBError> {
   use crate::db::schema::hand::dsl::*;
-  let result = hand
-    .filter(time.gt(1))
-    .select(Hand::as_select())
-    .load(conn)
-    .map_err(|e| DBError::err(DBErrorType::Select, e))?;
+  diesel::select(diesel::dsl::exists(hand.filter(id.eq(hand_id))))
+    .get_result(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Insert the hand with its actions, blinds, hole cards, boards and table events
+pub fn insert_hand_detail(
+  conn: &mut SqliteConnection,
+  hand_detail: &HandDetail,
+) -> Result<(), DBError> {
+  let actions: Vec<models::Action> = hand_detail.get_actions();
+  insert_actions(conn, &actions)?;
+
+  let (small_blind, big_blind) = hand_detail.get_blinds();
+  insert_blind(conn, &small_blind)?;
+  insert_blind(conn, &big_blind)?;
+
+  let hand = hand_detail.get_hand();
+  insert_hand(conn, &hand)?;
+
+  let hole_cards: Vec<models::HoleCard> = hand_detail.get_hole_cards();
+  insert_hole_cards(conn, &hole_cards)?;
+
+  let (boards, winners) = hand_detail.get_boards();
+  insert_boards(conn, &boards, &winners)?;
+
+  let events: Vec<models::Event> = hand_detail.get_events();
+  insert_events(conn, &events)
+}
+
+/// Hands written by `insert_new_hands`
+#[derive(Default)]
+pub struct Inserted<'a> {
+  pub hands: Vec<&'a HandDetail>,
+  pub errors: Vec<DBError>, // one per hand that couldn't be written
+}
 
-  for h in result {
-    println!("h : {:#?}", h);
+/// Insert a batch of hands in a single transaction, skipping the hands
+/// already in the database, and add them to the stats of their players.
+/// Each hand is written in its own savepoint, a hand that fails is rolled
+/// back and reported without losing the rest of the batch.
+pub fn insert_new_hands<'a>(
+  conn: &mut SqliteConnection,
+  hands_detail: &'a [HandDetail],
+) -> Result<Inserted<'a>, DBError> {
+  conn.transaction::<_, DBError, _>(|conn| {
+    let mut inserted = Inserted::default();
+    for hand_detail in hands_detail {
+      if hand_exists(conn, hand_detail.id)? {
+        continue;
+      }
+      // a nested transaction is a savepoint
+      match conn.transaction(|conn| insert_hand_detail(conn, hand_detail)) {
+        Ok(()) => inserted.hands.push(hand_detail),
+        Err(e) => inserted.errors.push(e.for_hand(hand_detail.id)),
+      }
+    }
+    update_players(conn, &inserted.hands)?;
+    Ok(inserted)
+  })
+}
+
+/// Add the hands to the stats of every player seated, real and play money
+/// stats are kept apart
+pub fn update_players(
+  conn: &mut SqliteConnection,
+  hands_detail: &[&HandDetail],
+) -> Result<(), DBError> {
+  let mut played: HashMap<(&str, bool), Vec<&HandDetail>> = HashMap::new();
+  for hand_detail in hands_detail {
+    for player in hand_detail.players.iter().flatten() {
+      played
+        .entry((player.name.as_str(), hand_detail.real_money))
+        .or_default()
+        .push(hand_detail);
+    }
+  }
+
+  for ((player_name, money), hands) in played {
+    let mut player = match get_player(conn, player_name, money)? {
+      Some(player) => player,
+      None => {
+        let mut player = models::Player::new(player_name);
+        player.real_money = money;
+        player
+      }
+    };
+    stats::add_hands(&mut player, &hands);
+    diesel::replace_into(schema::player::table)
+      .values(&player)
+      .execute(conn)
+      .map_err(|e| DBError::err(DBErrorType::Insert, e))?;
   }
   Ok(())
 }
 
-pub fn get_players(
+/// Compute the stats of every player again from the hands stored, after a
+/// change of the stats. Return the number of hands read.
+pub fn rebuild_players<F>(conn: &mut SqliteConnection, mut on_batch: F) -> Result<usize, DBError>
+where
+  F: FnMut(usize),
+{
+  const BATCH_SIZE: i64 = 1000;
+  conn.transaction::<_, DBError, _>(|conn| {
+    diesel::delete(schema::player::table)
+      .execute(conn)
+      .map_err(|e| DBError::err(DBErrorType::Insert, e))?;
+
+    let mut read = 0;
+    loop {
+      let contents: Vec<String> = {
+        use crate::db::schema::hand::dsl::*;
+        // in the order played, as on import
+        hand
+          .order((time.asc(), id.asc()))
+          .limit(BATCH_SIZE)
+          .offset(read as i64)
+          .select(content)
+          .load(conn)
+          .map_err(|e| DBError::err(DBErrorType::Select, e))?
+      };
+      if contents.is_empty() {
+        return Ok(read);
+      }
+      read += contents.len();
+
+      let mut hands_detail = Vec::new();
+      for content in &contents {
+        match parse::parse_hand(content) {
+          Ok(hand_detail) => hands_detail.push(hand_detail),
+          Err(e) => log::error!("Hand skipped : {}", e),
+        }
+      }
+      update_players(conn, &hands_detail.iter().collect::<Vec<&HandDetail>>())?;
+      on_batch(read);
+    }
+  })
+}
+
+pub fn get_hand(conn: &mut SqliteConnection, hand_id: i64) -> Result<models::Hand, DBError> {
+  use crate::db::models::Hand;
+  use crate::db::schema::hand::dsl::*;
+  hand
+    .filter(id.eq(hand_id))
+    .select(Hand::as_select())
+    .first(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+pub fn get_player(
   conn: &mut SqliteConnection,
-  names: Vec<&str>,
+  player_name: &str,
+  money: bool,
+) -> Result<Option<models::Player>, DBError> {
+  use crate::db::models::Player;
+  use crate::db::schema::player::dsl::{name, player, real_money};
+
+  player
+    .filter(name.eq(player_name))
+    .filter(real_money.eq(money))
+    .select(Player::as_select())
+    .first(conn)
+    .optional()
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Every player, the ones with the most hands first
+pub fn get_all_players(
+  conn: &mut SqliteConnection,
+  money: bool,
 ) -> Result<Vec<models::Player>, DBError> {
   use crate::db::models::Player;
-  use crate::db::schema::player::dsl::{name, player};
+  use crate::db::schema::player::dsl::{nb_hand, player, real_money};
 
-  let result = player
-    .filter(name.eq_any(names))
+  player
+    .filter(real_money.eq(money))
+    .order(nb_hand.desc())
     .select(Player::as_select())
     .load(conn)
-    .map_err(|e| DBError::err(DBErrorType::Select, e))?;
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Accounts that wrote the histories, from the "Dealt to" line of the hands
+pub fn get_heroes(conn: &mut SqliteConnection) -> Result<Vec<String>, DBError> {
+  use crate::db::schema::hand::dsl::{hand, hero};
+
+  hand
+    .filter(hero.is_not_null())
+    .select(hero.assume_not_null())
+    .distinct()
+    .order(hero)
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
 
-  Ok(result)
+// hands where the player is seated
+pub(crate) fn played_by(
+  player_name: &str,
+) -> Box<dyn BoxableExpression<schema::hand::table, Sqlite, SqlType = Bool>> {
+  use crate::db::schema::hand::dsl::*;
+  let name = player_name.to_string();
+  Box::new(
+    player1
+      .eq(name.clone())
+      .or(player2.eq(name.clone()))
+      .or(player3.eq(name.clone()))
+      .or(player4.eq(name.clone()))
+      .or(player5.eq(name.clone()))
+      .or(player6.eq(name.clone()))
+      .or(player7.eq(name.clone()))
+      .or(player8.eq(name.clone()))
+      .or(player9.eq(name)),
+  )
+}
+
+/// Hands of the player, or of everyone, the most recent first
+pub fn search_hands(
+  conn: &mut SqliteConnection,
+  player_name: Option<&str>,
+  money: Option<bool>,
+  since: Option<i64>,
+  limit: i64,
+  offset: i64,
+) -> Result<Vec<models::Hand>, DBError> {
+  use crate::db::models::Hand;
+  use crate::db::schema::hand::dsl::*;
+
+  let mut query = hand.into_boxed();
+  if let Some(player_name) = player_name {
+    query = query.filter(played_by(player_name));
+  }
+  if let Some(money) = money {
+    query = query.filter(real_money.eq(money));
+  }
+  if let Some(since) = since {
+    query = query.filter(time.ge(since));
+  }
+  query
+    .order((time.desc(), id.desc()))
+    .limit(limit)
+    .offset(offset)
+    .select(Hand::as_select())
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Hands of the player, or of everyone, with an id above `after` in the
+/// order they were played, to read them by batches while others are imported
+pub fn hands_after(
+  conn: &mut SqliteConnection,
+  player_name: Option<&str>,
+  money: bool,
+  after: i64,
+  limit: i64,
+) -> Result<Vec<models::Hand>, DBError> {
+  use crate::db::models::Hand;
+  use crate::db::schema::hand::dsl::*;
+
+  let mut query = hand.filter(id.gt(after)).into_boxed();
+  if let Some(player_name) = player_name {
+    query = query.filter(played_by(player_name));
+  }
+  query
+    .filter(real_money.eq(money))
+    .order(id.asc())
+    .limit(limit)
+    .select(Hand::as_select())
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Hands dealt to one of the accounts, as `hands_after`
+pub fn hero_hands_after(
+  conn: &mut SqliteConnection,
+  aliases: &[String],
+  money: bool,
+  after: i64,
+  limit: i64,
+) -> Result<Vec<models::Hand>, DBError> {
+  use crate::db::models::Hand;
+  use crate::db::schema::hand::dsl::*;
+
+  hand
+    .filter(id.gt(after))
+    .filter(hero.eq_any(aliases))
+    .filter(real_money.eq(money))
+    .order(id.asc())
+    .limit(limit)
+    .select(Hand::as_select())
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+pub fn count_hands(conn: &mut SqliteConnection, player_name: &str) -> Result<i64, DBError> {
+  use crate::db::schema::hand::dsl::hand;
+
+  hand
+    .filter(played_by(player_name))
+    .count()
+    .get_result(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Number of table events of each kind for a player, to see how often they
+/// sit out or rely on the disconnect protection
+pub fn count_events(
+  conn: &mut SqliteConnection,
+  player_name: &str,
+) -> Result<Vec<(String, i64)>, DBError> {
+  use crate::db::schema::event::dsl::{event, kind, player};
+
+  event
+    .filter(player.eq(player_name))
+    .group_by(kind)
+    .select((kind, diesel::dsl::count_star()))
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Position of the panels saved for the table size, empty when never moved
+pub fn get_hud_seats(
+  conn: &mut SqliteConnection,
+  size: i32,
+) -> Result<Vec<models::HudSeat>, DBError> {
+  use crate::db::models::HudSeat;
+  use crate::db::schema::hud_seat::dsl::{hud_seat, seat, table_size};
+
+  hud_seat
+    .filter(table_size.eq(size))
+    .order(seat)
+    .select(HudSeat::as_select())
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+pub fn save_hud_seats(
+  conn: &mut SqliteConnection,
+  seats: &[models::HudSeat],
+) -> Result<usize, DBError> {
+  diesel::replace_into(schema::hud_seat::table)
+    .values(seats)
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))
+}
+
+pub fn get_hud_profiles(conn: &mut SqliteConnection) -> Result<Vec<models::HudProfile>, DBError> {
+  use crate::db::models::HudProfile;
+  use crate::db::schema::hud_profile::dsl::{hud_profile, name};
+
+  hud_profile
+    .order(name)
+    .select(HudProfile::as_select())
+    .load(conn)
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Profile used by the HUD, none until one is chosen
+pub fn get_active_hud_profile(
+  conn: &mut SqliteConnection,
+) -> Result<Option<models::HudProfile>, DBError> {
+  use crate::db::models::HudProfile;
+  use crate::db::schema::hud_profile::dsl::{active, hud_profile};
+
+  hud_profile
+    .filter(active.eq(true))
+    .select(HudProfile::as_select())
+    .first(conn)
+    .optional()
+    .map_err(|e| DBError::err(DBErrorType::Select, e))
+}
+
+/// Insert the profile or replace the one with the same name, keeping it
+/// active if it was
+pub fn save_hud_profile(
+  conn: &mut SqliteConnection,
+  profile_name: &str,
+  profile_content: &str,
+) -> Result<usize, DBError> {
+  use crate::db::schema::hud_profile::dsl::{content, hud_profile, name};
+
+  diesel::insert_into(hud_profile)
+    .values((name.eq(profile_name), content.eq(profile_content)))
+    .on_conflict(name)
+    .do_update()
+    .set(content.eq(profile_content))
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))
+}
+
+/// Make the profile the only active one, return false if there's no such profile
+pub fn set_active_hud_profile(
+  conn: &mut SqliteConnection,
+  profile_name: &str,
+) -> Result<bool, DBError> {
+  use crate::db::schema::hud_profile::dsl::{active, hud_profile, name};
+
+  conn.transaction::<_, DBError, _>(|conn| {
+    diesel::update(hud_profile)
+      .set(active.eq(false))
+      .execute(conn)?;
+    let updated = diesel::update(hud_profile.filter(name.eq(profile_name)))
+      .set(active.eq(true))
+      .execute(conn)?;
+    Ok(updated == 1)
+  })
+}
+
+pub fn delete_hud_profile(
+  conn: &mut SqliteConnection,
+  profile_name: &str,
+) -> Result<usize, DBError> {
+  use crate::db::schema::hud_profile::dsl::{hud_profile, name};
+
+  diesel::delete(hud_profile.filter(name.eq(profile_name)))
+    .execute(conn)
+    .map_err(|e| DBError::err(DBErrorType::Insert, e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use diesel::connection::SimpleConnection;
+
+  const HANDS: &str = include_str!("../../test/test_hands.txt");
+
+  #[test]
+  fn failing_hand_keeps_the_batch() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let mut conn = connect(":memory:").unwrap();
+    conn
+      .batch_execute(&format!(
+        "CREATE TRIGGER broken BEFORE INSERT ON hand WHEN NEW.id = {}
+         BEGIN SELECT RAISE(ABORT, 'broken hand'); END;",
+        hands[0].id
+      ))
+      .unwrap();
+
+    let inserted = insert_new_hands(&mut conn, &hands).unwrap();
+    assert_eq!(inserted.hands.len(), 1);
+    assert_eq!(inserted.hands[0].id, hands[1].id);
+    assert_eq!(inserted.errors.len(), 1);
+    let error = inserted.errors[0].to_string();
+    assert!(error.starts_with("Insert "));
+    assert!(error.ends_with(&format!("(hand {})", hands[0].id)));
+
+    // the rows written before the failure are rolled back with the hand
+    let actions: i64 = schema::action::table
+      .filter(schema::action::hand.eq(hands[0].id))
+      .count()
+      .get_result(&mut conn)
+      .unwrap();
+    assert_eq!(actions, 0);
+    assert!(hand_exists(&mut conn, hands[1].id).unwrap());
+  }
+
+  #[test]
+  fn heroes_of_the_hands() {
+    let mut conn = connect(":memory:").unwrap();
+    assert!(get_heroes(&mut conn).unwrap().is_empty());
+    let hands = parse::parse_str(HANDS).unwrap();
+    insert_new_hands(&mut conn, &hands).unwrap();
+    // another account at the same table, the next hand
+    let other = HANDS
+      .replace("PokerZhyte", "Alt")
+      .replace("#249638850870", "#249638850871");
+    insert_new_hands(&mut conn, &parse::parse_str(&other).unwrap()[..1]).unwrap();
+    assert_eq!(get_heroes(&mut conn).unwrap(), vec!["Alt", "PokerZhyte"]);
+  }
 }
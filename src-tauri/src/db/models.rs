@@ -1,5 +1,6 @@
 use crate::db::schema;
 use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Queryable, Selectable, Insertable)]
 #[diesel(table_name = schema::action)]
@@ -15,6 +16,43 @@ pub struct Action {
   pub allin: bool,
 }
 
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = schema::board)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Board {
+  pub hand: i64,
+  pub run: i32, // 0, 1 for the second board of a hand run twice
+  pub card1: String,
+  pub card2: String,
+  pub card3: String,
+  pub card4: String,
+  pub card5: String,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = schema::winner)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Winner {
+  pub hand: i64,
+  pub run: i32,
+  pub player: String,
+  pub amount: f32,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = schema::event)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Event {
+  pub player: String,
+  pub hand: i64,
+  pub kind: String,
+  pub moment: String,
+  pub sequence: i32,
+  pub seat: Option<i32>,       // join
+  pub amount: Option<f32>,     // cash out
+  pub message: Option<String>, // chat
+}
+
 #[derive(Queryable, Selectable, Insertable)]
 #[diesel(table_name = schema::blind)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -32,7 +70,7 @@ pub struct Hand {
   pub id: i64,
   pub content: String,
   pub real_money: bool,
-  pub time: i64,
+  pub time: i64, // UTC timestamp
   pub table_name: String,
   pub table_size: i32,
   pub winner: String,
@@ -51,6 +89,13 @@ pub struct Hand {
   pub card3: String,
   pub card4: String,
   pub card5: String,
+  pub format: String,            // cash, zoom
+  pub local_offset: Option<i32>, // seconds to add to time to get the local time of the player
+  pub currency: String,          // USD, EUR, GBP, CAD, INR or PLAY for play money
+  pub language: String,          // en, fr, de, es, pt, it
+  pub hero: Option<String>,
+  pub small_limit: f32,
+  pub big_limit: f32,
 }
 
 #[derive(Queryable, Selectable, Insertable)]
@@ -63,7 +108,26 @@ pub struct HoleCard {
   pub card2: String,
 }
 
-#[derive(Queryable, Selectable, Debug, Insertable)]
+#[derive(Queryable, Selectable, Insertable, Debug)]
+#[diesel(table_name = schema::hud_profile)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HudProfile {
+  pub name: String,
+  pub content: String, // JSON of hud_profile::HudProfile
+  pub active: bool,
+}
+
+#[derive(Queryable, Selectable, Insertable, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = schema::hud_seat)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct HudSeat {
+  pub table_size: i32,
+  pub seat: i32,
+  pub x: f32, // from the left of the window, 0 to 1
+  pub y: f32, // from the top of the window, 0 to 1
+}
+
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq, Insertable, Serialize)]
 #[diesel(table_name = schema::player)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct Player {
@@ -86,6 +150,11 @@ pub struct Player {
   pub nb_call: f32,
   pub nb_bet: f32,
   pub nb_raise: f32,
+  pub wtsd: f32, // went to showdown, when the flop was seen
+  pub wsd: f32,  // won at showdown, a board of a hand run twice counts for its part
+  pub nb_can_wtsd: f32,
+  pub nb_can_wsd: f32,
+  pub winnings: f32, // big blinds won per 100 hands, every board of a hand run twice
 }
 
 impl Player {
@@ -110,6 +179,11 @@ impl Player {
       nb_can_cbet: 0.0,
       nb_can_fold_cbet: 0.0,
       nb_can_squeeze: 0.0,
+      wtsd: 0.0,
+      wsd: 0.0,
+      nb_can_wtsd: 0.0,
+      nb_can_wsd: 0.0,
+      winnings: 0.0,
     }
   }
 }
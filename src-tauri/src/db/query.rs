@@ -0,0 +1,570 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{models, played_by, schema, DBError, DBErrorType};
+use crate::parse::{self, Action, HandDetail};
+use crate::range::{Combo, Range};
+use crate::replay::Street;
+
+// Search of the hands. The filters on the columns of the hand table run in
+// SQL, the ones needing the actions or the cards run on the hands parsed
+// again from their content, like the stats.
+
+// hands read at once when the filters need the content
+const BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+  Utg,
+  Mp,
+  Hj,
+  Co,
+  Btn,
+  Sb,
+  Bb,
+}
+
+/// What a player does on a street, or faces from another player
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineAction {
+  Fold,
+  Check,
+  Call,
+  Bet, // first bet of the street, the open raise preflop
+  Raise,
+  ThreeBet,
+  FourBet,
+  CheckRaise,
+  AllIn,
+}
+
+/// "3-bet preflop" or, faced, "faced a check-raise on the flop"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+  pub street: Street,
+  pub action: LineAction,
+  #[serde(default)]
+  pub faced: bool, // done by another player before the player acted again
+}
+
+/// Filters of a search, all optional. The cards, the position and the lines
+/// are the ones of `player`, or of the hero of the hand when none is given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HandFilter {
+  pub player: Option<String>,
+  pub hero: Option<String>, // account that wrote the history
+  pub real_money: Option<bool>,
+  pub big_limits: Vec<f32>, // stakes by their big blind, any when empty
+  pub table_size: Option<i32>,
+  pub since: Option<i64>, // UTC timestamps
+  pub until: Option<i64>,
+  pub min_pot: Option<f32>,
+  pub max_pot: Option<f32>,
+  pub hole_cards: Option<Range>, // "AKs, QQ+", read from the text
+  pub position: Option<Position>,
+  pub street: Option<Street>, // street reached, at least
+  pub showdown: Option<bool>,
+  pub all_in: Option<bool>,
+  pub lines: Vec<Line>, // all of them
+}
+
+/// Last hand read by a search, the next page starts after it. The hands
+/// imported meanwhile don't move the pages.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+  pub time: i64,
+  pub id: i64,
+}
+
+/// Hands found, the most recent first. `next` continues the search, none
+/// once every hand was read.
+#[derive(Debug)]
+pub struct HandPage {
+  pub hands: Vec<models::Hand>,
+  pub next: Option<Cursor>,
+}
+
+impl HandFilter {
+  // filters that can't run in SQL
+  fn needs_content(&self) -> bool {
+    self.hole_cards.is_some()
+      || self.position.is_some()
+      || self.street.is_some()
+      || self.showdown.is_some()
+      || self.all_in.is_some()
+      || !self.lines.is_empty()
+  }
+
+  fn sql(
+    &self,
+    after: Option<Cursor>,
+  ) -> schema::hand::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+    use crate::db::schema::hand::dsl::*;
+
+    let mut query = hand.into_boxed();
+    // in the order of the search, the most recent first
+    if let Some(after) = after {
+      query = query.filter(
+        time
+          .lt(after.time)
+          .or(time.eq(after.time).and(id.lt(after.id))),
+      );
+    }
+    if let Some(player_name) = &self.player {
+      query = query.filter(played_by(player_name));
+    }
+    if let Some(name) = &self.hero {
+      query = query.filter(hero.eq(name.clone()));
+    }
+    if let Some(money) = self.real_money {
+      query = query.filter(real_money.eq(money));
+    }
+    if !self.big_limits.is_empty() {
+      query = query.filter(big_limit.eq_any(self.big_limits.clone()));
+    }
+    if let Some(size) = self.table_size {
+      query = query.filter(table_size.eq(size));
+    }
+    if let Some(since) = self.since {
+      query = query.filter(time.ge(since));
+    }
+    if let Some(until) = self.until {
+      query = query.filter(time.lt(until));
+    }
+    if let Some(min_pot) = self.min_pot {
+      query = query.filter(pot.ge(min_pot));
+    }
+    if let Some(max_pot) = self.max_pot {
+      query = query.filter(pot.le(max_pot));
+    }
+    query.order((time.desc(), id.desc()))
+  }
+
+  /// Whether the hand passes the filters needing the actions and the cards
+  pub fn matches(&self, hand: &HandDetail) -> bool {
+    let name = self
+      .player
+      .as_deref()
+      .or(hand.hero.as_ref().map(|hero| hero.name.as_str()));
+    let name = match name {
+      Some(name) => name,
+      // nobody to look at, the hand of a player only watching the table
+      None if self.needs_player() => return false,
+      None => "",
+    };
+    if let Some(range) = &self.hole_cards {
+      let cards = seat(hand, name).and_then(|seat| hand.players_card[seat as usize - 1].as_ref());
+      match cards {
+        Some(cards) if Combo::from_cards(cards).map_or(false, |combo| range.contains(&combo)) => (),
+        _ => return false,
+      }
+    }
+    if let Some(wanted) = self.position {
+      if position(hand, name) != Some(wanted) {
+        return false;
+      }
+    }
+    if let Some(wanted) = self.street {
+      if street_reached(hand) < street_index(wanted) {
+        return false;
+      }
+    }
+    if let Some(showdown) = self.showdown {
+      if hand.showdown != showdown {
+        return false;
+      }
+    }
+    if let Some(all_in) = self.all_in {
+      if has_all_in(hand) != all_in {
+        return false;
+      }
+    }
+    self.lines.iter().all(|line| line_matches(hand, name, line))
+  }
+
+  fn needs_player(&self) -> bool {
+    self.hole_cards.is_some() || self.position.is_some() || !self.lines.is_empty()
+  }
+}
+
+/// Hands passing the filter, the search starts after the hand of the cursor,
+/// from the most recent one without, and stops once `limit` hands are found
+pub fn search(
+  conn: &mut SqliteConnection,
+  filter: &HandFilter,
+  limit: i64,
+  after: Option<Cursor>,
+) -> Result<HandPage, DBError> {
+  if limit < 1 {
+    return Err(DBError::err(
+      DBErrorType::Select,
+      format!("a search of {} hands", limit),
+    ));
+  }
+  let cursor = |hand: &models::Hand| Cursor {
+    time: hand.time,
+    id: hand.id,
+  };
+  if !filter.needs_content() {
+    let hands: Vec<models::Hand> = filter
+      .sql(after)
+      .limit(limit)
+      .select(models::Hand::as_select())
+      .load(conn)
+      .map_err(|e| DBError::err(DBErrorType::Select, e))?;
+    let next = match hands.last() {
+      Some(last) if hands.len() as i64 == limit => Some(cursor(last)),
+      _ => None,
+    };
+    return Ok(HandPage { hands, next });
+  }
+
+  let mut hands = Vec::new();
+  let mut read = after;
+  loop {
+    let batch: Vec<models::Hand> = filter
+      .sql(read)
+      .limit(BATCH_SIZE)
+      .select(models::Hand::as_select())
+      .load(conn)
+      .map_err(|e| DBError::err(DBErrorType::Select, e))?;
+    let last_batch = (batch.len() as i64) < BATCH_SIZE;
+    for hand in batch {
+      read = Some(cursor(&hand));
+      let hand_detail = match parse::parse_hand(&hand.content) {
+        Ok(hand_detail) => hand_detail,
+        Err(e) => {
+          log::error!("Hand {} skipped : {}", hand.id, e);
+          continue;
+        }
+      };
+      if filter.matches(&hand_detail) {
+        hands.push(hand);
+        if hands.len() as i64 == limit {
+          return Ok(HandPage { hands, next: read });
+        }
+      }
+    }
+    if last_batch {
+      return Ok(HandPage { hands, next: None });
+    }
+  }
+}
+
+fn seat(hand: &HandDetail, name: &str) -> Option<u8> {
+  hand
+    .players
+    .iter()
+    .flatten()
+    .find(|player| player.name == name)
+    .map(|player| player.position)
+}
+
+/// Position of the player from the button, the players before the cutoff
+/// are under the gun, middle position and hijack
+pub fn position(hand: &HandDetail, name: &str) -> Option<Position> {
+  // seats from the one after the button, the button last
+  let mut seats: Vec<(u8, &str)> = hand
+    .players
+    .iter()
+    .flatten()
+    .map(|player| (player.position, player.name.as_str()))
+    .collect();
+  seats.sort_by_key(|&(position, _)| (position <= hand.button_position, position));
+  let index = seats.iter().position(|&(_, player)| player == name)?;
+  let count = seats.len();
+  if count == 2 {
+    // heads-up, the button posts the small blind
+    return Some(if index == 1 {
+      Position::Btn
+    } else {
+      Position::Bb
+    });
+  }
+  let from_button = count - 1 - index;
+  Some(match (index, from_button) {
+    (0, _) => Position::Sb,
+    (1, _) => Position::Bb,
+    (_, 0) => Position::Btn,
+    (2, _) => Position::Utg,
+    (_, 1) => Position::Co,
+    (_, 2) => Position::Hj,
+    _ => Position::Mp,
+  })
+}
+
+fn street_index(street: Street) -> usize {
+  match street {
+    Street::Preflop => 0,
+    Street::Flop => 1,
+    Street::Turn => 2,
+    Street::River => 3,
+  }
+}
+
+fn street_reached(hand: &HandDetail) -> usize {
+  if hand.river_card.is_some() {
+    3
+  } else if hand.turn_card.is_some() {
+    2
+  } else if hand.flop_card.is_some() {
+    1
+  } else {
+    0
+  }
+}
+
+fn has_all_in(hand: &HandDetail) -> bool {
+  [&hand.preflop, &hand.flop, &hand.turn, &hand.river]
+    .iter()
+    .flat_map(|actions| actions.iter())
+    .any(|action| {
+      matches!(
+        action,
+        Action::Call(_, _, true) | Action::Bet(_, _, true) | Action::Raise(_, _, _, true)
+      )
+    })
+}
+
+fn line_matches(hand: &HandDetail, name: &str, line: &Line) -> bool {
+  let moves = moves(hand, line.street);
+  if !line.faced {
+    return moves
+      .iter()
+      .any(|(player, kinds)| *player == name && kinds.contains(&line.action));
+  }
+  // another player did it and the player had to answer
+  moves.iter().enumerate().any(|(index, (player, kinds))| {
+    *player != name
+      && kinds.contains(&line.action)
+      && moves[index + 1..].iter().any(|(player, _)| *player == name)
+  })
+}
+
+// actions of the street with what each of them is
+fn moves(hand: &HandDetail, street: Street) -> Vec<(&str, Vec<LineAction>)> {
+  let actions = match street {
+    Street::Preflop => &hand.preflop,
+    Street::Flop => &hand.flop,
+    Street::Turn => &hand.turn,
+    Street::River => &hand.river,
+  };
+  // the big blind is the first bet preflop
+  let mut bets = if street == Street::Preflop { 1 } else { 0 };
+  let mut checked: Vec<&str> = Vec::new();
+  let mut moves = Vec::new();
+  for action in actions {
+    let (player, mut kinds, all_in) = match action {
+      Action::Fold(player) | Action::Leave(player) => (player, vec![LineAction::Fold], false),
+      Action::Check(player) => {
+        checked.push(&player.name);
+        (player, vec![LineAction::Check], false)
+      }
+      Action::Call(player, _, all_in) => (player, vec![LineAction::Call], *all_in),
+      Action::Bet(player, _, all_in) | Action::Raise(player, _, _, all_in) => {
+        bets += 1;
+        let first = bets == if street == Street::Preflop { 2 } else { 1 };
+        let mut kinds = vec![if first {
+          LineAction::Bet
+        } else {
+          LineAction::Raise
+        }];
+        match bets {
+          3 => kinds.push(LineAction::ThreeBet),
+          4 => kinds.push(LineAction::FourBet),
+          _ => (),
+        }
+        if checked.contains(&player.name.as_str()) {
+          kinds.push(LineAction::CheckRaise);
+        }
+        (player, kinds, *all_in)
+      }
+      Action::UncalledBet(_, _) => continue,
+    };
+    if all_in {
+      kinds.push(LineAction::AllIn);
+    }
+    moves.push((player.name.as_str(), kinds));
+  }
+  moves
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HANDS: &str = include_str!("../../test/test_hands.txt");
+
+  #[test]
+  fn filter_hands() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let hand = &hands[0];
+    let hero = hand.hero.as_ref().unwrap();
+    let cards = hand.players_card[hero.position as usize - 1]
+      .clone()
+      .unwrap();
+
+    let filter = |filter: HandFilter| filter.matches(hand);
+    assert!(filter(HandFilter::default()));
+    let class = format!("{}{}", &cards[0][..1], &cards[1][..1]);
+    let range = |range: &str| Some(Range::parse(range).unwrap());
+    assert!(filter(HandFilter {
+      hole_cards: range(&format!("22+, {}", class)),
+      ..Default::default()
+    }));
+    assert!(!filter(HandFilter {
+      hole_cards: range("AA"),
+      ..Default::default()
+    }));
+    assert_eq!(
+      filter(HandFilter {
+        street: Some(Street::Flop),
+        ..Default::default()
+      }),
+      hand.flop_card.is_some()
+    );
+    assert!(filter(HandFilter {
+      position: position(hand, &hero.name),
+      ..Default::default()
+    }));
+
+    // the range comes as text from the frontend
+    let filter: HandFilter = serde_json::from_str(r#"{"hole_cards": "QQ+, AKs"}"#).unwrap();
+    assert_eq!(filter.hole_cards, range("QQ+, AKs"));
+    assert!(serde_json::from_str::<HandFilter>(r#"{"hole_cards": "AKx"}"#).is_err());
+  }
+
+  #[test]
+  fn action_lines() {
+    // UTG opens, the cutoff folds, the button 3-bets, UTG calls and
+    // check-raises the flop
+    let hand = parse::parse_hand(
+      "PokerStars Hand #249638850870:  Hold'em No Limit ($0.01/$0.02 USD) - 2024/03/26 22:02:04 CET [2024/03/26 17:02:04 ET]
+Table 'Ostara III' 6-max Seat #3 is the button
+Seat 1: sidneivl ($2 in chips)
+Seat 2: captelie52 ($2 in chips)
+Seat 3: Savva08 ($2 in chips)
+Seat 4: Cazunga ($2 in chips)
+Seat 5: PokerZhyte ($2 in chips)
+Cazunga: posts small blind $0.01
+PokerZhyte: posts big blind $0.02
+*** HOLE CARDS ***
+Dealt to PokerZhyte [Ah Kh]
+sidneivl: raises $0.04 to $0.06
+captelie52: folds
+Savva08: raises $0.12 to $0.18
+Cazunga: folds
+PokerZhyte: folds
+sidneivl: calls $0.12
+*** FLOP *** [2c 7d Jh]
+sidneivl: checks
+Savva08: bets $0.20
+sidneivl: raises $1.62 to $1.82 and is all-in
+Savva08: folds
+Uncalled bet ($1.62) returned to sidneivl
+sidneivl collected $0.77 from pot
+*** SUMMARY ***
+Total pot $0.77 | Rake $0.02
+Board [2c 7d Jh]
+Seat 1: sidneivl collected ($0.77)
+Seat 2: captelie52 folded before Flop (didn't bet)
+Seat 3: Savva08 (button) folded on the Flop
+Seat 4: Cazunga (small blind) folded before Flop
+Seat 5: PokerZhyte (big blind) folded before Flop",
+    )
+    .unwrap();
+
+    let line = |street: Street, action: LineAction, faced: bool| Line {
+      street,
+      action,
+      faced,
+    };
+    let lines = |player: &str, lines: Vec<Line>| {
+      HandFilter {
+        player: Some(player.to_string()),
+        lines,
+        ..Default::default()
+      }
+      .matches(&hand)
+    };
+    assert!(lines(
+      "Savva08",
+      vec![
+        line(Street::Preflop, LineAction::ThreeBet, false),
+        line(Street::Flop, LineAction::CheckRaise, true),
+      ]
+    ));
+    assert!(lines(
+      "sidneivl",
+      vec![
+        line(Street::Preflop, LineAction::Bet, false),
+        line(Street::Preflop, LineAction::ThreeBet, true),
+        line(Street::Flop, LineAction::AllIn, false),
+      ]
+    ));
+    // the cutoff folded to the open, before the 3-bet
+    assert!(lines(
+      "captelie52",
+      vec![line(Street::Preflop, LineAction::Bet, true)]
+    ));
+    assert!(!lines(
+      "captelie52",
+      vec![line(Street::Preflop, LineAction::ThreeBet, true)]
+    ));
+    assert!(lines(
+      "PokerZhyte",
+      vec![line(Street::Preflop, LineAction::ThreeBet, true)]
+    ));
+
+    assert_eq!(position(&hand, "sidneivl"), Some(Position::Utg));
+    assert_eq!(position(&hand, "captelie52"), Some(Position::Co));
+    assert_eq!(position(&hand, "Savva08"), Some(Position::Btn));
+    assert_eq!(position(&hand, "Cazunga"), Some(Position::Sb));
+    assert_eq!(position(&hand, "PokerZhyte"), Some(Position::Bb));
+    assert!(HandFilter {
+      all_in: Some(true),
+      street: Some(Street::Flop),
+      showdown: Some(false),
+      hole_cards: Some(Range::parse("AKs").unwrap()),
+      ..Default::default()
+    }
+    .matches(&hand));
+  }
+
+  #[test]
+  fn pages() {
+    let mut conn = crate::db::connect(":memory:").unwrap();
+    let hands = parse::parse_str(HANDS).unwrap();
+    crate::db::insert_new_hands(&mut conn, &hands).unwrap();
+    let ids = |page: &HandPage| page.hands.iter().map(|hand| hand.id).collect::<Vec<_>>();
+    let filter = HandFilter::default();
+    assert!(search(&mut conn, &filter, 0, None).is_err());
+
+    let first = search(&mut conn, &filter, 1, None).unwrap();
+    assert_eq!(ids(&first), vec![hands[1].id]);
+    // a hand imported between the pages doesn't move them
+    let newer = HANDS
+      .replace("#249638850870", "#249638850880")
+      .replace("2024/03/26", "2025/03/26");
+    crate::db::insert_new_hands(&mut conn, &parse::parse_str(&newer).unwrap()[..1]).unwrap();
+    let second = search(&mut conn, &filter, 1, first.next).unwrap();
+    assert_eq!(ids(&second), vec![hands[0].id]);
+    let last = search(&mut conn, &filter, 1, second.next).unwrap();
+    assert!(last.hands.is_empty());
+    assert_eq!(last.next, None);
+
+    // the same pages with a filter on the content
+    let filter = HandFilter {
+      street: Some(Street::Preflop),
+      ..Default::default()
+    };
+    let first = search(&mut conn, &filter, 2, None).unwrap();
+    assert_eq!(first.hands[1].id, hands[1].id);
+    let second = search(&mut conn, &filter, 2, first.next).unwrap();
+    assert_eq!(ids(&second), vec![hands[0].id]);
+    assert_eq!(second.next, None);
+  }
+}
@@ -24,6 +24,33 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    board (id) {
+        id -> Integer,
+        hand -> BigInt,
+        run -> Integer,
+        card1 -> Text,
+        card2 -> Text,
+        card3 -> Text,
+        card4 -> Text,
+        card5 -> Text,
+    }
+}
+
+diesel::table! {
+    event (id) {
+        id -> Integer,
+        player -> Text,
+        hand -> BigInt,
+        kind -> Text,
+        moment -> Text,
+        sequence -> Integer,
+        seat -> Nullable<Integer>,
+        amount -> Nullable<Float>,
+        message -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     hand (id) {
         id -> BigInt,
@@ -48,6 +75,13 @@ diesel::table! {
         card3 -> Text,
         card4 -> Text,
         card5 -> Text,
+        format -> Text,
+        local_offset -> Nullable<Integer>,
+        currency -> Text,
+        language -> Text,
+        hero -> Nullable<Text>,
+        small_limit -> Float,
+        big_limit -> Float,
     }
 }
 
@@ -61,6 +95,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    hud_profile (name) {
+        name -> Text,
+        content -> Text,
+        active -> Bool,
+    }
+}
+
+diesel::table! {
+    hud_seat (table_size, seat) {
+        table_size -> Integer,
+        seat -> Integer,
+        x -> Float,
+        y -> Float,
+    }
+}
+
 diesel::table! {
     player (name, real_money) {
         name -> Text,
@@ -82,17 +133,40 @@ diesel::table! {
         nb_call -> Float,
         nb_bet -> Float,
         nb_raise -> Float,
+        wtsd -> Float,
+        wsd -> Float,
+        nb_can_wtsd -> Float,
+        nb_can_wsd -> Float,
+        winnings -> Float,
+    }
+}
+
+diesel::table! {
+    winner (id) {
+        id -> Integer,
+        hand -> BigInt,
+        run -> Integer,
+        player -> Text,
+        amount -> Float,
     }
 }
 
 diesel::joinable!(action -> hand (hand));
 diesel::joinable!(blind -> hand (hand));
+diesel::joinable!(board -> hand (hand));
+diesel::joinable!(event -> hand (hand));
 diesel::joinable!(holeCard -> hand (hand));
+diesel::joinable!(winner -> hand (hand));
 
 diesel::allow_tables_to_appear_in_same_query!(
     action,
     blind,
+    board,
+    event,
     hand,
     holeCard,
+    hud_profile,
+    hud_seat,
     player,
+    winner,
 );
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::import;
+
+// Find the HandHistory folders written by the PokerStars clients. On Linux
+// the client runs under Wine, or the histories are read from the Windows
+// partition of a dual boot. Every site has its own folder (PokerStars,
+// PokerStars.BE, PokerStars.FR, ...) with a sub-folder per account.
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+  Native,    // the client of this system
+  Wine,      // a Wine prefix, Lutris, Bottles or Proton
+  Partition, // a mounted Windows partition
+}
+
+/// Folder of the histories of an account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryFolder {
+  pub path: PathBuf,
+  pub hero: String,
+  pub site: String, // name of the client folder, "PokerStars.BE"
+  pub origin: Origin,
+  pub files: usize, // history files found, archives included
+}
+
+/// Places to look at, the defaults of the system from `roots`
+#[derive(Debug, Default)]
+pub struct Roots {
+  pub app_data: Vec<(PathBuf, Origin)>, // folders holding the client folders
+  pub wine_prefixes: Vec<PathBuf>,
+  pub mounts: Vec<PathBuf>, // mounted partitions, or the folders they're mounted in
+}
+
+impl Roots {
+  pub fn new(home: &Path) -> Self {
+    let mut roots = Roots::default();
+
+    if let Ok(local) = env::var("LOCALAPPDATA") {
+      roots.app_data.push((PathBuf::from(local), Origin::Native));
+    }
+    roots
+      .app_data
+      .push((home.join("Library/Application Support"), Origin::Native));
+
+    if let Ok(prefix) = env::var("WINEPREFIX") {
+      roots.wine_prefixes.push(PathBuf::from(prefix));
+    }
+    roots.wine_prefixes.push(home.join(".wine"));
+    // launchers keep a prefix per game
+    for launcher in [
+      ".local/share/wineprefixes",
+      "Games",
+      ".local/share/bottles/bottles",
+      ".var/app/com.usebottles.bottles/data/bottles/bottles",
+    ] {
+      roots.wine_prefixes.extend(sub_dirs(&home.join(launcher)));
+    }
+    for steam in [".steam/steam", ".local/share/Steam"] {
+      let compatdata = home.join(steam).join("steamapps/compatdata");
+      roots.wine_prefixes.extend(
+        sub_dirs(&compatdata)
+          .into_iter()
+          .map(|game| game.join("pfx")),
+      );
+    }
+
+    roots.mounts.push(PathBuf::from("/mnt"));
+    roots.mounts.extend(sub_dirs(Path::new("/media")));
+    roots.mounts.extend(sub_dirs(Path::new("/run/media")));
+    roots
+  }
+}
+
+/// Every account folder found on the system
+pub fn discover() -> Vec<HistoryFolder> {
+  match dirs::home_dir() {
+    Some(home) => discover_in(&Roots::new(&home)),
+    None => discover_in(&Roots::default()),
+  }
+}
+
+pub fn discover_in(roots: &Roots) -> Vec<HistoryFolder> {
+  let mut app_data = roots.app_data.clone();
+  for prefix in &roots.wine_prefixes {
+    for user in sub_dirs(&prefix.join("drive_c/users")) {
+      app_data.push((user.join("AppData/Local"), Origin::Wine));
+      // Windows XP layout, still used by old prefixes
+      app_data.push((user.join("Local Settings/Application Data"), Origin::Wine));
+    }
+  }
+  for mount in &roots.mounts {
+    // the partition itself or one level below, /mnt/windows
+    for partition in std::iter::once(mount.clone()).chain(sub_dirs(mount)) {
+      for user in sub_dirs(&partition.join("Users")) {
+        app_data.push((user.join("AppData/Local"), Origin::Partition));
+      }
+    }
+  }
+
+  let mut folders = Vec::new();
+  for (dir, origin) in app_data {
+    for site in sub_dirs(&dir) {
+      let site_name = file_name(&site);
+      if !is_site(&site_name) {
+        continue;
+      }
+      for hero in sub_dirs(&site.join("HandHistory")) {
+        if folders
+          .iter()
+          .any(|folder: &HistoryFolder| folder.path == hero)
+        {
+          continue;
+        }
+        folders.push(HistoryFolder {
+          files: import::collect_files(&hero).len(),
+          hero: file_name(&hero),
+          site: site_name.clone(),
+          origin,
+          path: hero,
+        });
+      }
+    }
+  }
+  folders
+}
+
+// PokerStars or PokerStars.<country>, not PokerStarsUpdate
+fn is_site(name: &str) -> bool {
+  let name = name.to_lowercase();
+  name == "pokerstars" || name.starts_with("pokerstars.")
+}
+
+// folders under the path, none when it can't be read
+fn sub_dirs(path: &Path) -> Vec<PathBuf> {
+  let mut dirs: Vec<PathBuf> = match std::fs::read_dir(path) {
+    Ok(entries) => entries
+      .flatten()
+      .map(|entry| entry.path())
+      .filter(|path| path.is_dir())
+      .collect(),
+    Err(_) => Vec::new(),
+  };
+  dirs.sort();
+  dirs
+}
+
+fn file_name(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn history(path: &Path, files: &[&str]) {
+    std::fs::create_dir_all(path).unwrap();
+    for file in files {
+      std::fs::write(path.join(file), "").unwrap();
+    }
+  }
+
+  #[test]
+  fn wine_and_partitions() {
+    let root = env::temp_dir().join(format!("inspector-discover-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let wine = root.join("home/.wine");
+    history(
+      &wine.join("drive_c/users/noah/AppData/Local/PokerStars.BE/HandHistory/PokerZhyte"),
+      &["HH20240326 Ostara III.txt", "notes.log"],
+    );
+    history(
+      &wine.join("drive_c/users/noah/AppData/Local/PokerStars.BE/HandHistory/PokerZhyte/play"),
+      &["HH20240329 Zoom.txt"],
+    );
+    history(
+      &wine.join("drive_c/users/noah/AppData/Local/PokerStarsUpdate/HandHistory/x"),
+      &[],
+    );
+    let mnt = root.join("mnt");
+    history(
+      &mnt.join("windows/Users/noah/AppData/Local/PokerStars.FR/HandHistory/Zhyte"),
+      &["HH.zip"],
+    );
+    history(&mnt.join("windows/Users/noah/AppData/Local/Other"), &[]);
+
+    let roots = Roots {
+      app_data: Vec::new(),
+      wine_prefixes: vec![wine.clone()],
+      mounts: vec![mnt],
+    };
+    let folders: Vec<(String, String, Origin, usize)> = discover_in(&roots)
+      .into_iter()
+      .map(|folder| (folder.hero, folder.site, folder.origin, folder.files))
+      .collect();
+    assert_eq!(
+      folders,
+      vec![
+        // the play money sub-folder is part of the account
+        (
+          "PokerZhyte".to_string(),
+          "PokerStars.BE".to_string(),
+          Origin::Wine,
+          2
+        ),
+        (
+          "Zhyte".to_string(),
+          "PokerStars.FR".to_string(),
+          Origin::Partition,
+          1
+        ),
+      ]
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -0,0 +1,95 @@
+use dotenvy::dotenv;
+use serde::Serialize;
+use std::env;
+use std::fmt;
+
+use crate::db::models;
+use crate::parse::HandDetail;
+use crate::stats;
+
+/// Accounts played by the team. They're read from `HERO_ALIASES`, a comma
+/// separated list of screen names, "HERO_ALIASES=PokerZhyte,OtherAccount".
+#[derive(Default, Debug, Clone)]
+pub struct Heroes {
+  pub aliases: Vec<String>,
+}
+
+impl Heroes {
+  pub fn new(aliases: &[&str]) -> Self {
+    Heroes {
+      aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+    }
+  }
+
+  pub fn from_env() -> Self {
+    dotenv().ok();
+    let aliases = env::var("HERO_ALIASES").unwrap_or_default();
+    Heroes {
+      aliases: aliases
+        .split(',')
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .map(String::from)
+        .collect(),
+    }
+  }
+
+  pub fn is_hero(&self, name: &str) -> bool {
+    self.aliases.iter().any(|alias| alias == name)
+  }
+}
+
+/// Stats of each hero account and of all the accounts merged
+#[derive(Serialize)]
+pub struct HeroReport {
+  pub accounts: Vec<models::Player>,
+  pub merged: models::Player,
+}
+
+impl HeroReport {
+  pub fn new(heroes: &Heroes, real_money: bool) -> Self {
+    let player = |name: &str| {
+      let mut player = models::Player::new(name);
+      player.real_money = real_money;
+      player
+    };
+    HeroReport {
+      accounts: heroes.aliases.iter().map(|alias| player(alias)).collect(),
+      merged: player(&heroes.aliases.join("+")),
+    }
+  }
+
+  /// Add the hands to the stats, read by batches
+  pub fn add(&mut self, hands: &[HandDetail], heroes: &Heroes) {
+    for account in &mut self.accounts {
+      let alias = account.name.clone();
+      stats::add_hero(account, hands, &Heroes::new(&[alias.as_str()]));
+    }
+    stats::add_hero(&mut self.merged, hands, heroes);
+  }
+}
+
+impl fmt::Display for HeroReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(
+      f,
+      "{:<24}{:>8}{:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+      "account", "hands", "vpip", "pfr", "af", "wtsd", "wsd", "bb/100"
+    )?;
+    for player in self.accounts.iter().chain([&self.merged]) {
+      writeln!(
+        f,
+        "{:<24}{:>8}{:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}",
+        player.name,
+        player.nb_hand,
+        player.vpip,
+        player.pfr,
+        player.af,
+        player.wtsd,
+        player.wsd,
+        player.winnings
+      )?;
+    }
+    Ok(())
+  }
+}
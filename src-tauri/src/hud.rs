@@ -0,0 +1,334 @@
+use diesel::SqliteConnection;
+use serde::Serialize;
+use std::f32::consts::PI;
+use std::time::Instant;
+
+use crate::db::{self, models, DBError};
+use crate::hud_profile::{self, HudProfile, StatValue};
+use crate::registry::{TableRegistry, TableSnapshot};
+
+// The HUD opens a window over each table of the registry, the stat panel of
+// each seat placed by the layout of the table size. The windows themselves
+// are handled by a `WindowHost`, Tauri in the app, so the HUD can be tested
+// without a display.
+
+/// Size of the HUD window, the default size of a table in the client
+pub const WINDOW_WIDTH: f64 = 792.;
+pub const WINDOW_HEIGHT: f64 = 546.;
+
+/// Where the HUD finds the open tables and the position of the panels
+pub trait TableSource {
+  fn tables(&mut self) -> Result<Vec<TableSnapshot>, DBError>;
+  fn layout(&mut self, table_size: u8) -> Result<Vec<models::HudSeat>, DBError>;
+  fn profile(&mut self) -> Result<HudProfile, DBError>;
+}
+
+/// Window system showing the HUD
+pub trait WindowHost {
+  fn open(&mut self, label: &str, title: &str) -> Result<(), String>;
+  fn close(&mut self, label: &str);
+  fn show(&mut self, label: &str, frame: &HudFrame);
+  fn set_click_through(&mut self, label: &str, enabled: bool);
+}
+
+/// Content of a HUD window
+#[derive(Debug, Clone, Serialize)]
+pub struct HudFrame {
+  pub table_name: String,
+  pub table_size: u8,
+  pub button_position: u8,
+  pub panels: Vec<Panel>,
+}
+
+/// Stat panel of a seat
+#[derive(Debug, Clone, Serialize)]
+pub struct Panel {
+  pub position: u8,
+  pub x: f32,
+  pub y: f32,
+  pub name: String,
+  pub stack: f32,
+  pub stats: Option<models::Player>,
+  pub values: Vec<StatValue>, // stats of the HUD profile, in order
+}
+
+/// Windows opened by the HUD, one per table
+#[derive(Default)]
+pub struct Hud {
+  windows: Vec<(String, String)>, // label and table key
+  click_through: bool,
+}
+
+impl Hud {
+  /// Open the windows of the new tables, close the ones of the tables gone
+  /// and update the panels of the others
+  pub fn refresh(
+    &mut self,
+    host: &mut impl WindowHost,
+    source: &mut impl TableSource,
+  ) -> Result<(), DBError> {
+    let tables = source.tables()?;
+    let profile = source.profile()?;
+
+    self.windows.retain(|(label, key)| {
+      let open = tables.iter().any(|table| &table.key == key);
+      if !open {
+        host.close(label);
+      }
+      open
+    });
+
+    for table in &tables {
+      let label = window_label(&table.key);
+      if !self.windows.iter().any(|(open, _)| *open == label) {
+        if let Err(e) = host.open(&label, &table.key) {
+          log::error!("Can't open the HUD of {} : {}", table.key, e);
+          continue;
+        }
+        host.set_click_through(&label, self.click_through);
+        self.windows.push((label.clone(), table.key.clone()));
+      }
+      let layout = source.layout(table.table_size)?;
+      host.show(&label, &frame(table, &layout, &profile));
+    }
+    Ok(())
+  }
+
+  /// Let the clicks go through the windows to the tables below
+  pub fn set_click_through(&mut self, host: &mut impl WindowHost, enabled: bool) {
+    self.click_through = enabled;
+    for (label, _) in &self.windows {
+      host.set_click_through(label, enabled);
+    }
+  }
+
+  pub fn close_all(&mut self, host: &mut impl WindowHost) {
+    for (label, _) in self.windows.drain(..) {
+      host.close(&label);
+    }
+  }
+}
+
+/// The tables of the registry, the layouts saved in the database
+pub struct LiveTables<'a> {
+  pub registry: &'a TableRegistry,
+  pub conn: &'a mut SqliteConnection,
+}
+
+impl TableSource for LiveTables<'_> {
+  fn tables(&mut self) -> Result<Vec<TableSnapshot>, DBError> {
+    self.registry.snapshot(self.conn, Instant::now())
+  }
+
+  fn layout(&mut self, table_size: u8) -> Result<Vec<models::HudSeat>, DBError> {
+    db::get_hud_seats(self.conn, table_size as i32)
+  }
+
+  fn profile(&mut self) -> Result<HudProfile, DBError> {
+    hud_profile::active(self.conn)
+  }
+}
+
+/// Panels around an ellipse, seat 1 at the top right going clockwise like in
+/// the client
+pub fn default_layout(table_size: u8) -> Vec<models::HudSeat> {
+  (1..=table_size)
+    .map(|seat| {
+      let angle = -PI / 3. + 2. * PI * (seat - 1) as f32 / table_size as f32;
+      models::HudSeat {
+        table_size: table_size as i32,
+        seat: seat as i32,
+        x: 0.5 + 0.42 * angle.cos(),
+        y: 0.45 + 0.38 * angle.sin(),
+      }
+    })
+    .collect()
+}
+
+fn frame(table: &TableSnapshot, layout: &[models::HudSeat], profile: &HudProfile) -> HudFrame {
+  let default = default_layout(table.table_size);
+  let panels = table
+    .seats
+    .iter()
+    .map(|seat| {
+      let position = seat.player.position as i32;
+      let place = layout
+        .iter()
+        .chain(&default)
+        .find(|place| place.seat == position);
+      Panel {
+        position: seat.player.position,
+        x: place.map_or(0., |place| place.x),
+        y: place.map_or(0., |place| place.y),
+        name: seat.player.name.clone(),
+        stack: seat.player.stack,
+        stats: seat.stats.clone(),
+        values: profile.render(seat.stats.as_ref()),
+      }
+    })
+    .collect();
+  HudFrame {
+    table_name: table.table_name.clone(),
+    table_size: table.table_size,
+    button_position: table.button_position,
+    panels,
+  }
+}
+
+// window labels only accept letters, digits and `-/:_`
+pub fn window_label(key: &str) -> String {
+  let name: String = key
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+  format!("hud-{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::registry::{SeatSnapshot, SeatedPlayer};
+
+  #[derive(Default)]
+  struct Tables {
+    tables: Vec<TableSnapshot>,
+    layout: Vec<models::HudSeat>,
+  }
+
+  impl TableSource for Tables {
+    fn tables(&mut self) -> Result<Vec<TableSnapshot>, DBError> {
+      Ok(self.tables.clone())
+    }
+
+    fn layout(&mut self, table_size: u8) -> Result<Vec<models::HudSeat>, DBError> {
+      Ok(
+        self
+          .layout
+          .iter()
+          .filter(|seat| seat.table_size == table_size as i32)
+          .cloned()
+          .collect(),
+      )
+    }
+
+    fn profile(&mut self) -> Result<HudProfile, DBError> {
+      Ok(HudProfile::default())
+    }
+  }
+
+  #[derive(Default)]
+  struct Windows {
+    open: Vec<String>,
+    shown: Vec<HudFrame>,
+    click_through: Vec<(String, bool)>,
+  }
+
+  impl WindowHost for Windows {
+    fn open(&mut self, label: &str, _title: &str) -> Result<(), String> {
+      self.open.push(label.to_string());
+      Ok(())
+    }
+
+    fn close(&mut self, label: &str) {
+      self.open.retain(|open| open != label);
+    }
+
+    fn show(&mut self, _label: &str, frame: &HudFrame) {
+      self.shown.push(frame.clone());
+    }
+
+    fn set_click_through(&mut self, label: &str, enabled: bool) {
+      self.click_through.push((label.to_string(), enabled));
+    }
+  }
+
+  fn table_snapshot(table_name: &str, players: usize) -> TableSnapshot {
+    TableSnapshot {
+      key: table_name.to_string(),
+      table_name: table_name.to_string(),
+      table_size: 6,
+      button_position: 1,
+      zoom: false,
+      last_hand: 1,
+      seats: (1..=players as u8)
+        .map(|position| SeatSnapshot {
+          player: SeatedPlayer {
+            position,
+            name: format!("player{}", position),
+            stack: 2.,
+          },
+          stats: None,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn window_per_table() {
+    let mut source = Tables {
+      tables: vec![
+        table_snapshot("Ostara III", 6),
+        table_snapshot("Zeta II", 2),
+      ],
+      layout: vec![models::HudSeat {
+        table_size: 6,
+        seat: 2,
+        x: 0.1,
+        y: 0.2,
+      }],
+    };
+    let mut windows = Windows::default();
+    let mut hud = Hud::default();
+    hud.refresh(&mut windows, &mut source).unwrap();
+    assert_eq!(windows.open, vec!["hud-Ostara_III", "hud-Zeta_II"]);
+
+    // the saved position replaces the default one
+    let panels = &windows.shown[0].panels;
+    assert_eq!(panels.len(), 6);
+    assert_eq!((panels[1].x, panels[1].y), (0.1, 0.2));
+    assert_eq!(panels[0].x, default_layout(6)[0].x);
+    assert_eq!(panels[0].values.len(), HudProfile::default().stats.len());
+
+    hud.set_click_through(&mut windows, true);
+    source.tables.remove(0);
+    source.tables.push(table_snapshot("Ostara IV", 3));
+    hud.refresh(&mut windows, &mut source).unwrap();
+    assert_eq!(windows.open, vec!["hud-Zeta_II", "hud-Ostara_IV"]);
+    // a new window follows the HUD setting
+    assert_eq!(
+      windows.click_through.last(),
+      Some(&("hud-Ostara_IV".to_string(), true))
+    );
+
+    hud.close_all(&mut windows);
+    assert!(windows.open.is_empty());
+  }
+
+  #[test]
+  fn window_per_zoom_table() {
+    let zoom = |key: &str| TableSnapshot {
+      key: key.to_string(),
+      zoom: true,
+      ..table_snapshot("NLHE 50/100 6 Max", 6)
+    };
+    let mut source = Tables {
+      tables: vec![zoom("NLHE 50/100 6 Max #1"), zoom("NLHE 50/100 6 Max #2")],
+      ..Default::default()
+    };
+    let mut windows = Windows::default();
+    let mut hud = Hud::default();
+    hud.refresh(&mut windows, &mut source).unwrap();
+    assert_eq!(
+      windows.open,
+      vec!["hud-NLHE_50_100_6_Max__1", "hud-NLHE_50_100_6_Max__2"]
+    );
+    assert!(windows
+      .shown
+      .iter()
+      .all(|frame| frame.table_name == "NLHE 50/100 6 Max"));
+
+    source.tables.remove(0);
+    hud.refresh(&mut windows, &mut source).unwrap();
+    assert_eq!(windows.open, vec!["hud-NLHE_50_100_6_Max__2"]);
+  }
+}
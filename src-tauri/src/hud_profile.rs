@@ -0,0 +1,271 @@
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{self, models, DBError, DBErrorType};
+
+// A HUD profile lists the stats of each panel. It's stored as JSON in the
+// `hud_profile` table, so stats can be added without a migration. The
+// profile shown is only kept in the table, not in the settings.
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+  Hands,
+  Vpip,
+  Pfr,
+  Af,
+  Pre3bet,
+  FoldPre3bet,
+  Cbet,
+  FoldCbet,
+  Squeeze,
+  Wtsd,
+  Wsd,
+}
+
+impl Stat {
+  /// Value shown, in percent except the hands and the aggression factor.
+  /// None when the player never had the occasion.
+  pub fn value(&self, player: &models::Player) -> Option<f32> {
+    let (value, percent) = match self {
+      Stat::Hands => (player.nb_hand, false),
+      Stat::Vpip => (player.vpip, true),
+      Stat::Pfr => (player.pfr, true),
+      Stat::Af => (player.af, false),
+      Stat::Pre3bet => (player.pre_3bet, true),
+      Stat::FoldPre3bet => (player.fold_pre_3bet, true),
+      Stat::Cbet => (player.cbet, true),
+      Stat::FoldCbet => (player.fold_cbet, true),
+      Stat::Squeeze => (player.squeeze, true),
+      Stat::Wtsd => (player.wtsd, true),
+      Stat::Wsd => (player.wsd, true),
+    };
+    // the stats module gives -1 when it can't divide
+    if value < 0. {
+      None
+    } else if percent {
+      Some(value * 100.)
+    } else {
+      Some(value)
+    }
+  }
+
+  /// Number of times the player could do the action
+  pub fn sample(&self, player: &models::Player) -> f32 {
+    match self {
+      Stat::Hands | Stat::Vpip | Stat::Pfr | Stat::Af => player.nb_hand,
+      Stat::Pre3bet => player.nb_can_pre_3bet,
+      Stat::FoldPre3bet => player.nb_can_fold_pre_3bet,
+      Stat::Cbet => player.nb_can_cbet,
+      Stat::FoldCbet => player.nb_can_fold_cbet,
+      Stat::Squeeze => player.nb_can_squeeze,
+      Stat::Wtsd => player.nb_can_wtsd,
+      Stat::Wsd => player.nb_can_wsd,
+    }
+  }
+
+  fn label(&self) -> &'static str {
+    match self {
+      Stat::Hands => "hands",
+      Stat::Vpip => "vpip",
+      Stat::Pfr => "pfr",
+      Stat::Af => "af",
+      Stat::Pre3bet => "3b",
+      Stat::FoldPre3bet => "f3b",
+      Stat::Cbet => "cb",
+      Stat::FoldCbet => "fcb",
+      Stat::Squeeze => "sqz",
+      Stat::Wtsd => "wtsd",
+      Stat::Wsd => "wsd",
+    }
+  }
+}
+
+/// Color of the value once it's above the limit, "VPIP > 40 red"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+  pub above: f32,
+  pub color: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatConfig {
+  pub stat: Stat,
+  #[serde(default)]
+  pub label: Option<String>, // name of the stat when none is given
+  #[serde(default)]
+  pub min_sample: u32, // occasions needed before showing the value
+  #[serde(default)]
+  pub decimals: u8,
+  #[serde(default)]
+  pub thresholds: Vec<Threshold>,
+}
+
+impl StatConfig {
+  pub fn new(stat: Stat) -> Self {
+    StatConfig {
+      stat,
+      label: None,
+      min_sample: 0,
+      decimals: 0,
+      thresholds: Vec::new(),
+    }
+  }
+
+  pub fn render(&self, player: Option<&models::Player>) -> StatValue {
+    let value = player
+      .filter(|player| self.stat.sample(player) >= self.min_sample as f32)
+      .and_then(|player| self.stat.value(player));
+    // the highest limit passed gives the color
+    let color = value.and_then(|value| {
+      self
+        .thresholds
+        .iter()
+        .filter(|threshold| value > threshold.above)
+        .max_by(|a, b| a.above.total_cmp(&b.above))
+        .map(|threshold| threshold.color.clone())
+    });
+    StatValue {
+      stat: self.stat,
+      label: self
+        .label
+        .clone()
+        .unwrap_or_else(|| self.stat.label().to_string()),
+      text: value.map_or("-".to_string(), |value| {
+        format!("{:.*}", self.decimals as usize, value)
+      }),
+      color,
+    }
+  }
+}
+
+/// A stat ready to be shown in a panel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatValue {
+  pub stat: Stat,
+  pub label: String,
+  pub text: String, // "-" until the sample is large enough
+  pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HudProfile {
+  pub name: String,
+  pub stats: Vec<StatConfig>, // in the order shown
+}
+
+impl Default for HudProfile {
+  fn default() -> Self {
+    let threshold = |above: f32, color: &str| Threshold {
+      above,
+      color: color.to_string(),
+    };
+    let stat = |stat: Stat, min_sample: u32, thresholds: Vec<Threshold>| StatConfig {
+      min_sample,
+      thresholds,
+      ..StatConfig::new(stat)
+    };
+    HudProfile {
+      name: "default".to_string(),
+      stats: vec![
+        stat(
+          Stat::Vpip,
+          10,
+          vec![threshold(25., "orange"), threshold(40., "red")],
+        ),
+        stat(Stat::Pfr, 10, vec![threshold(20., "orange")]),
+        StatConfig {
+          decimals: 1,
+          ..stat(Stat::Af, 20, vec![threshold(3., "red")])
+        },
+        stat(Stat::Pre3bet, 20, vec![threshold(10., "red")]),
+        stat(Stat::Cbet, 10, Vec::new()),
+        stat(Stat::Hands, 0, Vec::new()),
+      ],
+    }
+  }
+}
+
+impl HudProfile {
+  pub fn render(&self, player: Option<&models::Player>) -> Vec<StatValue> {
+    self.stats.iter().map(|stat| stat.render(player)).collect()
+  }
+
+  pub fn from_row(row: &models::HudProfile) -> Result<Self, DBError> {
+    serde_json::from_str(&row.content).map_err(|e| DBError::err(DBErrorType::Content, e))
+  }
+
+  pub fn to_json(&self) -> Result<String, DBError> {
+    serde_json::to_string(self).map_err(|e| DBError::err(DBErrorType::Content, e))
+  }
+}
+
+/// Profile chosen for the HUD, the default one until a profile is chosen
+pub fn active(conn: &mut SqliteConnection) -> Result<HudProfile, DBError> {
+  match db::get_active_hud_profile(conn)? {
+    Some(row) => HudProfile::from_row(&row),
+    None => Ok(HudProfile::default()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn player() -> models::Player {
+    let mut player = models::Player::new("sidneivl");
+    player.nb_hand = 30.;
+    player.vpip = 0.456;
+    player.af = 2.26;
+    player.nb_can_pre_3bet = 4.;
+    player.pre_3bet = 0.25;
+    player.cbet = -1.;
+    player
+  }
+
+  #[test]
+  fn render_stats() {
+    let profile = HudProfile::default();
+    let values: Vec<(String, String, Option<String>)> = profile
+      .render(Some(&player()))
+      .into_iter()
+      .map(|value| (value.label, value.text, value.color))
+      .collect();
+    let value = |label: &str, text: &str, color: Option<&str>| {
+      (label.to_string(), text.to_string(), color.map(String::from))
+    };
+    assert_eq!(
+      values,
+      vec![
+        value("vpip", "46", Some("red")),
+        value("pfr", "0", None),
+        value("af", "2.3", None),
+        value("3b", "-", None), // 4 occasions, 20 needed
+        value("cb", "-", None),
+        value("hands", "30", None),
+      ]
+    );
+
+    // nothing is known about a player never imported
+    assert!(profile.render(None).iter().all(|value| value.text == "-"));
+  }
+
+  #[test]
+  fn json_format() {
+    let profile: HudProfile = serde_json::from_str(
+      r#"{"name": "short", "stats": [
+        {"stat": "vpip", "thresholds": [{"above": 40, "color": "red"}]},
+        {"stat": "fold_cbet", "label": "fold cb", "min_sample": 5, "decimals": 1}
+      ]}"#,
+    )
+    .unwrap();
+    assert_eq!(profile.stats[0].min_sample, 0);
+    assert_eq!(profile.stats[1].stat, Stat::FoldCbet);
+    let row = models::HudProfile {
+      name: profile.name.clone(),
+      content: profile.to_json().unwrap(),
+      active: true,
+    };
+    assert_eq!(HudProfile::from_row(&row).unwrap(), profile);
+  }
+}
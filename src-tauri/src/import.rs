@@ -0,0 +1,228 @@
+use diesel::SqliteConnection;
+use rayon::prelude::*;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::sync_channel;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::db::{self, DBError};
+use crate::parse::split::{self, HandSlice};
+use crate::parse::{input, Diagnostic, HandDetail, ParseError, Profile};
+
+// number of hands sent at once to the database writer
+const BATCH_SIZE: usize = 500;
+// batches waiting for the writer before the parsers block
+const CHANNEL_SIZE: usize = 16;
+
+#[derive(Default, Debug, Clone)]
+pub struct Progress {
+  pub files_total: usize,
+  pub files_done: usize,
+  pub hands: usize,    // hands parsed
+  pub inserted: usize, // hands written to the database, already known hands are skipped
+  pub errors: usize,   // rejected hands, unreadable files and failed writes
+  pub elapsed: Duration,
+}
+
+impl Progress {
+  pub fn hands_per_sec(&self) -> f64 {
+    let seconds = self.elapsed.as_secs_f64();
+    if seconds == 0. {
+      0.
+    } else {
+      self.hands as f64 / seconds
+    }
+  }
+}
+
+impl fmt::Display for Progress {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "files {}/{}, hands {} ({} new), errors {}, {:.0} hands/s",
+      self.files_done,
+      self.files_total,
+      self.hands,
+      self.inserted,
+      self.errors,
+      self.hands_per_sec()
+    )
+  }
+}
+
+#[derive(Default, Debug)]
+pub struct ImportReport {
+  pub progress: Progress,
+  pub profile: Profile,
+  pub diagnostics: Vec<Diagnostic>,
+  pub file_errors: Vec<ParseError>,
+  pub db_errors: Vec<DBError>,
+}
+
+/// Import every history file found under the paths.
+/// Files are parsed in parallel, and so are the hands of each file, while a
+/// single writer inserts them in the database since SQLite only accepts one
+/// writer at a time. Hands that fail to parse are skipped and reported, as in
+/// `parse::parse_file_lenient`.
+pub fn import<F>(paths: &[PathBuf], conn: &mut SqliteConnection, on_progress: F) -> ImportReport
+where
+  F: Fn(&Progress) + Sync,
+{
+  let begin = Instant::now();
+  let files: Vec<PathBuf> = paths.iter().flat_map(|path| collect_files(path)).collect();
+  let report = Mutex::new(ImportReport {
+    progress: Progress {
+      files_total: files.len(),
+      ..Default::default()
+    },
+    ..Default::default()
+  });
+
+  // update the progress and notify without holding the lock during the callback
+  let notify = |update: &dyn Fn(&mut ImportReport)| {
+    let progress = {
+      let mut report = report.lock().unwrap();
+      update(&mut report);
+      report.progress.elapsed = begin.elapsed();
+      report.progress.clone()
+    };
+    on_progress(&progress);
+  };
+
+  let (sender, receiver) = sync_channel::<Vec<HandDetail>>(CHANNEL_SIZE);
+  std::thread::scope(|scope| {
+    scope.spawn(|| {
+      for hands in receiver {
+        let instant = Instant::now();
+        let result = db::insert_new_hands(conn, &hands);
+        let elapsed = instant.elapsed();
+        let (inserted, failed, errors) = match result {
+          Ok(inserted) => (inserted.hands.len(), inserted.errors.len(), inserted.errors),
+          Err(e) => (0, hands.len(), vec![e]),
+        };
+        notify(&|report| {
+          report.profile.insert += elapsed;
+          report.progress.inserted += inserted;
+          report.progress.errors += failed;
+        });
+        report.lock().unwrap().db_errors.extend(errors);
+      }
+    });
+
+    files.par_iter().for_each_with(sender, |sender, file| {
+      let mut profile = Profile::default();
+      let instant = Instant::now();
+      let sources = input::read_path(file);
+      profile.read += instant.elapsed();
+
+      let sources = match sources {
+        Ok(sources) => sources,
+        Err(e) => {
+          notify(&|report| {
+            report.progress.files_done += 1;
+            report.progress.errors += 1;
+            report.profile += &profile;
+          });
+          report.lock().unwrap().file_errors.push(e);
+          return;
+        }
+      };
+
+      let mut hands_count = 0;
+      let mut diagnostics = Vec::new();
+      for source in sources {
+        let (hands, source_diagnostics) = parse_source(&source, &mut profile);
+        hands_count += hands.len();
+        diagnostics.extend(source_diagnostics);
+        let mut hands = hands.into_iter().peekable();
+        while hands.peek().is_some() {
+          // the writer only stops once every sender is dropped, send can't fail
+          let _ = sender.send(hands.by_ref().take(BATCH_SIZE).collect());
+        }
+      }
+
+      notify(&|report| {
+        report.progress.files_done += 1;
+        report.progress.hands += hands_count;
+        report.progress.errors += diagnostics.len();
+        report.profile += &profile;
+      });
+      report.lock().unwrap().diagnostics.extend(diagnostics);
+    });
+  });
+
+  let mut report = report.into_inner().unwrap();
+  report.progress.elapsed = begin.elapsed();
+  report
+}
+
+// Parse the hands of a source in parallel. Each hand gets its own profile,
+// they are summed afterwards.
+fn parse_source(
+  source: &input::Source,
+  profile: &mut Profile,
+) -> (Vec<HandDetail>, Vec<Diagnostic>) {
+  let instant = Instant::now();
+  let content = input::normalize(&source.content);
+  let hands_content: Vec<HandSlice> = split::split(&content)
+    .filter(|hand| !hand.is_truncated())
+    .collect();
+  profile.split += instant.elapsed();
+
+  let results: Vec<(Result<HandDetail, ParseError>, Profile)> = hands_content
+    .par_iter()
+    .map(|hand| {
+      let mut hand_profile = Profile::default();
+      let result = HandDetail::parse_hand(hand.text, &mut hand_profile);
+      (result, hand_profile)
+    })
+    .collect();
+
+  let mut hands = Vec::new();
+  let mut diagnostics = Vec::new();
+  for (hand, (result, hand_profile)) in hands_content.iter().zip(results) {
+    *profile += &hand_profile;
+    match result {
+      Ok(h) => hands.push(h),
+      Err(error) => diagnostics.push(Diagnostic::new(&source.name, hand, error)),
+    }
+  }
+  (hands, diagnostics)
+}
+
+/// History files under the path. A file is always returned, directories are
+/// walked recursively keeping text files and archives.
+pub fn collect_files(path: &Path) -> Vec<PathBuf> {
+  if !path.is_dir() {
+    return vec![path.to_path_buf()];
+  }
+  let mut files = Vec::new();
+  let entries = match std::fs::read_dir(path) {
+    Ok(entries) => entries,
+    Err(e) => {
+      log::error!("Can't read directory {:?} : {}", path, e);
+      return files;
+    }
+  };
+  for entry in entries.flatten() {
+    let entry_path = entry.path();
+    if entry_path.is_dir() {
+      files.extend(collect_files(&entry_path));
+    } else if is_history_file(&entry_path) {
+      files.push(entry_path);
+    }
+  }
+  files.sort();
+  files
+}
+
+pub(crate) fn is_history_file(path: &Path) -> bool {
+  matches!(
+    path
+      .extension()
+      .map(|e| e.to_string_lossy().to_lowercase())
+      .as_deref(),
+    Some("txt") | Some("gz") | Some("zip")
+  )
+}
@@ -4,72 +4,76 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod cli;
+mod commands;
 mod db;
+mod discover;
+mod hero;
+mod hud;
+mod hud_profile;
+mod import;
 mod parse;
+mod range;
+mod registry;
+mod replay;
+mod settings;
 mod stats;
 mod track;
 
-use core::panic;
-use diesel::SqliteConnection;
-use parse::HandDetail;
-use std::path::Path;
-
-use crate::db::establish_connection;
+use crate::settings::Settings;
 
 fn main() {
-  let directory = "/mnt/windows/Users/noah/AppData/Local/PokerStars.BE/HandHistory/PokerZhyte/play";
-  let mut files = Vec::new();
-
-  // add file content in vectore
-  for entry in std::fs::read_dir(directory).unwrap() {
-    let entry = entry.unwrap();
-    let path = entry.path();
-    let path_str = path.to_str().unwrap();
-    files.push(path_str.to_string());
-  }
-
-  let file = "./test/test_hands.txt";
-
-  // parse the first file
-  let hands_detail = match parse::parse_file(file) {
-    Err(e) => panic!("Error {}\nparsing file : {:#?}", e, files[0]),
-    Ok(h) => h,
-  };
-  println!("Number of hands {}", hands_detail.len());
-
-  // compute the stats for PokerZhyte player
-  let mut poker_zhyte = db::models::Player::new("PokerZhyte");
-  stats::add(&mut poker_zhyte, &hands_detail);
-
-  // compute hand of DB from hand detail
-  let mut conn = establish_connection().unwrap();
-  update_db(&mut conn, &hands_detail);
-
   env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-  use std::sync::mpsc::channel;
-  let (tx, rx) = channel();
-
-  let path = Path::new(r"/home/noah/test");
-  if let Err(error) = track::watch(path, tx, rx) {
-    log::error!("Error : {error:?}");
-  }
-}
-
-// miss player
-fn update_db(conn: &mut SqliteConnection, hands_detail: &Vec<HandDetail>) {
-  for hand_detail in hands_detail {
-    let actions: Vec<db::models::Action> = hand_detail.get_actions();
-    db::insert_actions(conn, &actions);
-
-    let (small_blind, big_blind) = hand_detail.get_blinds();
-    db::insert_blind(conn, &small_blind);
-    db::insert_blind(conn, &big_blind);
-
-    let hand = hand_detail.get_hand();
-    db::insert_hand(conn, &hand);
+  let settings_path = settings::path();
+  let settings = Settings::load(&settings_path);
 
-    let hole_cards: Vec<db::models::HoleCard> = hand_detail.get_hole_cards();
-    db::insert_hole_cards(conn, &hole_cards);
+  // a command runs without the window, and stops on broken settings
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if !args.is_empty() {
+    std::process::exit(cli::main(&args, settings));
   }
+  // the default settings are used until the file is fixed
+  let settings = settings.unwrap_or_else(|e| {
+    log::error!("Settings ignored : {}", e);
+    Settings::default()
+  });
+  let conn = db::connect(&settings.database).expect("can't open the database");
+  let read = db::connect(&settings.database).expect("can't open the database");
+
+  tauri::Builder::default()
+    .manage(commands::AppState::new(conn, read, settings, settings_path))
+    .setup(|app| {
+      commands::setup(&app.handle());
+      Ok(())
+    })
+    .invoke_handler(tauri::generate_handler![
+      commands::get_hand_replay,
+      commands::get_replay_step,
+      commands::list_players,
+      commands::get_player_profile,
+      commands::search_hands,
+      commands::query_hands,
+      commands::import_folder,
+      commands::get_import_progress,
+      commands::start_tracking,
+      commands::stop_tracking,
+      commands::get_tables,
+      commands::refresh_hud,
+      commands::set_hud_click_through,
+      commands::get_hud_layout,
+      commands::save_hud_layout,
+      commands::list_hud_profiles,
+      commands::save_hud_profile,
+      commands::delete_hud_profile,
+      commands::set_active_hud_profile,
+      commands::get_settings,
+      commands::save_settings,
+      commands::get_range_matrix,
+      commands::get_hero_report,
+      commands::discover_history_folders,
+      commands::add_history_folders
+    ])
+    .run(tauri::generate_context!())
+    .expect("error while running tauri application");
 }
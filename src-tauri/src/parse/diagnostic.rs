@@ -0,0 +1,80 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::parse::split::HandSlice;
+use crate::parse::{start, HandDetail, ParseError};
+
+/// A hand rejected by the lenient parser
+#[derive(Debug)]
+pub struct Diagnostic {
+  pub file: String,
+  pub index: usize,    // position of the hand in the file
+  pub offset: usize,   // byte offset of the hand once the BOM and carriage returns are removed
+  pub id: Option<i64>, // None when even the header can't be read
+  pub message: String,
+  pub content: String, // raw text of the hand, kept for bug reports
+}
+
+impl Diagnostic {
+  pub fn new(file: &str, hand: &HandSlice, error: ParseError) -> Self {
+    let id = hand
+      .text
+      .lines()
+      .next()
+      .and_then(|line| start::extract_id(line).ok());
+    Diagnostic {
+      file: file.to_string(),
+      index: hand.index,
+      offset: hand.offset,
+      id,
+      message: error.to_string(),
+      content: hand.text.to_string(),
+    }
+  }
+}
+
+// one line per rejected hand
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let message = self
+      .message
+      .split_whitespace()
+      .collect::<Vec<&str>>()
+      .join(" ");
+    write!(
+      f,
+      "{} hand #{} ({}, byte {}): {}",
+      self.file,
+      self.index,
+      self
+        .id
+        .map_or("unknown id".to_string(), |id| id.to_string()),
+      self.offset,
+      message
+    )
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct LenientReport {
+  pub hands: Vec<HandDetail>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Append the raw text of the rejected hands to the quarantine file.
+/// Hands are separated by blank lines, like in a history file, so the
+/// quarantine can be parsed again once the parser is fixed.
+pub fn write_quarantine(path: &Path, diagnostics: &[Diagnostic]) -> std::io::Result<()> {
+  if diagnostics.is_empty() {
+    return Ok(());
+  }
+  let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+  for diagnostic in diagnostics {
+    writeln!(file, "{}", diagnostic.content.trim_end())?;
+    writeln!(file)?;
+    writeln!(file)?;
+  }
+  Ok(())
+}
@@ -2,6 +2,7 @@ use std::fmt;
 
 #[derive(Debug, Clone)]
 pub enum ParseErrorType {
+  File,
   Start,
   Preflop,
   Flop,
@@ -21,6 +22,7 @@ pub struct ParseError {
 impl fmt::Display for ParseError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match &self.t {
+      ParseErrorType::File => write!(f, "File error:\n{}", self.msg),
       ParseErrorType::Start => write!(f, "Start error:\n{}", self.msg),
       ParseErrorType::Preflop => write!(f, "Preflop error:\n{}", self.msg),
       ParseErrorType::Flop => write!(f, "Flop error:\n{}", self.msg),
@@ -1,15 +1,22 @@
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, Utc};
+use std::fmt;
+
+use crate::parse::Language;
 
 // This structure will be used to compute the stats of the player
 #[derive(Default, Debug, PartialEq)]
 pub struct HandDetail {
   pub id: i64, // u32 is too small
   pub content: String,
-  pub real_money: bool,
-  pub date: DateTime<FixedOffset>,
+  pub format: GameFormat,
+  pub currency: Currency,
+  pub language: Language, // language of the client that wrote the history
+  pub real_money: bool,   // false for play money
+  pub date: DateTime<Utc>,
+  pub local_date: Option<DateTime<FixedOffset>>, // time shown to the player, when the header has it
   pub small_limit: f32,
   pub big_limit: f32,
-  pub table_name: String,
+  pub table_name: String, // pool name for Zoom, the table changes every hand
   pub table_size: u8,
   pub button_position: u8, // usefull to shift position and guess real position
   pub players: [Option<Player>; 9],
@@ -17,17 +24,57 @@ pub struct HandDetail {
   pub big_blind: Blind,
   pub end: End, // NOTE: not used
   pub players_card: [Option<[String; 2]>; 9],
+  pub hero: Option<Player>, // player of the "Dealt to" line, the account that wrote the history
   pub preflop: Vec<Action>,
   pub flop: Vec<Action>,
   pub turn: Vec<Action>,
   pub river: Vec<Action>,
+  pub events: Vec<TableEvent>, // sit out, disconnection, chat, ... in the order of the history
   pub flop_card: Option<[String; 3]>,
   pub turn_card: Option<String>,
   pub river_card: Option<String>,
+  pub boards: Vec<Board>, // two boards when the hand is run twice, the cards above are the first one
+  pub showdown: bool,
 }
 
 use crate::db::models;
 impl HandDetail {
+  pub fn is_zoom(&self) -> bool {
+    self.format == GameFormat::Zoom
+  }
+
+  pub(crate) fn board_mut(&mut self, run: usize) -> &mut Board {
+    if self.boards.len() <= run {
+      self.boards.resize_with(run + 1, Board::default);
+    }
+    &mut self.boards[run]
+  }
+
+  /// Part of the boards won by the player, 0.5 when winning one of the two
+  /// boards of a hand run twice
+  pub fn board_share(&self, name: &str) -> f32 {
+    if self.boards.is_empty() {
+      return 0.;
+    }
+    let won = self
+      .boards
+      .iter()
+      .filter(|board| board.winners.iter().any(|(player, _)| player.name == name))
+      .count();
+    won as f32 / self.boards.len() as f32
+  }
+
+  /// Amount collected by the player over every board
+  pub fn collected(&self, name: &str) -> f32 {
+    self
+      .boards
+      .iter()
+      .flat_map(|board| &board.winners)
+      .filter(|(player, _)| player.name == name)
+      .map(|(_, amount)| amount)
+      .sum()
+  }
+
   pub fn get_actions(&self) -> Vec<models::Action> {
     let mut actions: Vec<models::Action> = Vec::new();
     let mut sequence = 0;
@@ -154,9 +201,69 @@ impl HandDetail {
         .river_card
         .as_ref()
         .map_or(String::new(), |c| c.clone()),
+      format: self.format.to_string(),
+      local_offset: self.local_date.map(|date| date.offset().local_minus_utc()),
+      currency: self.currency.to_string(),
+      language: self.language.to_string(),
+      hero: self.hero.as_ref().map(|hero| hero.name.clone()),
+      small_limit: self.small_limit,
+      big_limit: self.big_limit,
     }
   }
 
+  pub fn get_events(&self) -> Vec<models::Event> {
+    self
+      .events
+      .iter()
+      .enumerate()
+      .map(|(sequence, event)| {
+        let mut object = models::Event {
+          player: event.player.clone(),
+          hand: self.id,
+          kind: event.kind.to_string(),
+          moment: event.moment.to_string(),
+          sequence: sequence as i32,
+          seat: None,
+          amount: None,
+          message: None,
+        };
+        match &event.kind {
+          EventKind::Join(seat) => object.seat = Some(*seat as i32),
+          EventKind::Chat(message) => object.message = Some(message.clone()),
+          EventKind::CashOut(amount) => object.amount = Some(*amount),
+          _ => (),
+        }
+        object
+      })
+      .collect()
+  }
+
+  pub fn get_boards(&self) -> (Vec<models::Board>, Vec<models::Winner>) {
+    let mut boards = Vec::new();
+    let mut winners = Vec::new();
+    for (run, board) in self.boards.iter().enumerate() {
+      let card = |i: usize| board.cards.get(i).cloned().unwrap_or_default();
+      boards.push(models::Board {
+        hand: self.id,
+        run: run as i32,
+        card1: card(0),
+        card2: card(1),
+        card3: card(2),
+        card4: card(3),
+        card5: card(4),
+      });
+      for (player, amount) in &board.winners {
+        winners.push(models::Winner {
+          hand: self.id,
+          run: run as i32,
+          player: player.name.clone(),
+          amount: *amount,
+        });
+      }
+    }
+    (boards, winners)
+  }
+
   pub fn get_blinds(&self) -> (models::Blind, models::Blind) {
     // small and big
     let small = models::Blind {
@@ -199,6 +306,48 @@ impl HandDetail {
   }
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum GameFormat {
+  #[default]
+  Cash,
+  // Fast-fold: the player moves to a new table of the pool after every hand,
+  // so the seats don't map to the same players from one hand to the next
+  Zoom,
+}
+
+impl fmt::Display for GameFormat {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GameFormat::Cash => write!(f, "cash"),
+      GameFormat::Zoom => write!(f, "zoom"),
+    }
+  }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Currency {
+  #[default]
+  Usd,
+  Eur,
+  Gbp,
+  Cad,
+  Inr,
+  PlayMoney,
+}
+
+impl fmt::Display for Currency {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Currency::Usd => write!(f, "USD"),
+      Currency::Eur => write!(f, "EUR"),
+      Currency::Gbp => write!(f, "GBP"),
+      Currency::Cad => write!(f, "CAD"),
+      Currency::Inr => write!(f, "INR"),
+      Currency::PlayMoney => write!(f, "PLAY"),
+    }
+  }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Action {
   Call(Player, f32, bool),
@@ -211,6 +360,41 @@ pub enum Action {
   UncalledBet(Player, f32),
 }
 
+/// Line of the history that isn't a poker action
+#[derive(Debug, PartialEq)]
+pub struct TableEvent {
+  pub player: String, // not always seated, a player joining the table isn't dealt in
+  pub moment: &'static str, // start, preflop, flop, turn, river or showdown
+  pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+  SitOut,
+  Return,
+  TimedOut, // the disconnect protection acted for the player
+  Disconnected,
+  Connected,
+  Join(u8), // seat
+  Chat(String),
+  CashOut(f32),
+}
+
+impl fmt::Display for EventKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      EventKind::SitOut => write!(f, "sit_out"),
+      EventKind::Return => write!(f, "return"),
+      EventKind::TimedOut => write!(f, "timed_out"),
+      EventKind::Disconnected => write!(f, "disconnected"),
+      EventKind::Connected => write!(f, "connected"),
+      EventKind::Join(_) => write!(f, "join"),
+      EventKind::Chat(_) => write!(f, "chat"),
+      EventKind::CashOut(_) => write!(f, "cash_out"),
+    }
+  }
+}
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Player {
   pub name: String,
@@ -224,6 +408,12 @@ pub struct Blind {
   pub amount: f32,
 }
 
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Board {
+  pub cards: Vec<String>,
+  pub winners: Vec<(Player, f32)>, // players who collected on this board, a pot can be split
+}
+
 #[derive(Default, Debug, PartialEq)]
 pub struct End {
   pub pot: f32,
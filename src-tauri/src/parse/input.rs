@@ -0,0 +1,222 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use flate2::read::MultiGzDecoder;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use crate::parse::{ParseError, ParseErrorType};
+
+/// Text of a history file, possibly extracted from an archive
+pub struct Source {
+  pub name: String, // path of the file, "archive.zip:entry.txt" for archive entries
+  pub content: String,
+}
+
+/// Read the history files behind the path. Plain files give one source,
+/// `.gz` files are decompressed and `.zip` archives give one source per entry.
+pub fn read_path(path: &Path) -> Result<Vec<Source>, ParseError> {
+  let name = path.to_string_lossy().to_string();
+  let extension = path
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+
+  match extension.as_str() {
+    "gz" => {
+      let file =
+        File::open(path).map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+      let mut bytes = Vec::new();
+      MultiGzDecoder::new(file)
+        .read_to_end(&mut bytes)
+        .map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+      Ok(vec![Source {
+        name: name.trim_end_matches(".gz").to_string(),
+        content: decode(&bytes),
+      }])
+    }
+    "zip" => {
+      let file =
+        File::open(path).map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+      let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+      let mut sources = Vec::new();
+      for i in 0..archive.len() {
+        let mut entry = archive
+          .by_index(i)
+          .map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+        if entry.is_dir() {
+          continue;
+        }
+        let entry_name = format!("{}:{}", name, entry.name());
+        let mut bytes = Vec::new();
+        entry
+          .read_to_end(&mut bytes)
+          .map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &entry_name))?;
+        sources.push(Source {
+          name: entry_name,
+          content: decode(&bytes),
+        });
+      }
+      Ok(sources)
+    }
+    _ => {
+      let bytes =
+        fs::read(path).map_err(|e| ParseError::err_msg(ParseErrorType::File, e, &name))?;
+      Ok(vec![Source {
+        name,
+        content: decode(&bytes),
+      }])
+    }
+  }
+}
+
+/// Decode the raw bytes of a history file.
+/// PokerStars writes UTF-8 with a BOM, but older installs and some copies write
+/// UTF-16 or Windows-1252 (player names with accents), so we sniff the BOM,
+/// then the null bytes of UTF-16 without BOM, and fall back on Windows-1252
+/// when the content isn't valid UTF-8.
+pub fn decode(bytes: &[u8]) -> String {
+  if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+    encoding
+      .decode_without_bom_handling(&bytes[bom_length..])
+      .0
+      .into_owned()
+  } else if let Some(encoding) = guess_utf16(bytes) {
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+  } else {
+    match std::str::from_utf8(bytes) {
+      Ok(content) => content.to_string(),
+      Err(_) => WINDOWS_1252
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned(),
+    }
+  }
+}
+
+/// Remove the carriage returns and BOM left in the text.
+/// The content is only copied when there's something to remove.
+pub fn normalize(content: &str) -> Cow<'_, str> {
+  if content.contains(['\r', '\u{feff}']) {
+    Cow::Owned(content.replace(['\r', '\u{feff}'], ""))
+  } else {
+    Cow::Borrowed(content)
+  }
+}
+
+// ASCII text encoded in UTF-16 has a null byte every other byte
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+  let sample = &bytes[..bytes.len().min(1024) & !1];
+  if sample.is_empty() {
+    return None;
+  }
+  let half = sample.len() / 2;
+  let even_nulls = sample.iter().step_by(2).filter(|b| **b == 0).count();
+  let odd_nulls = sample
+    .iter()
+    .skip(1)
+    .step_by(2)
+    .filter(|b| **b == 0)
+    .count();
+  if odd_nulls * 2 > half && even_nulls * 10 < half {
+    Some(UTF_16LE)
+  } else if even_nulls * 2 > half && odd_nulls * 10 < half {
+    Some(UTF_16BE)
+  } else {
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Write;
+
+  const TEXT: &str =
+    "PokerStars Hand #1: Hold'em No Limit ($0.01/$0.02 USD)\nSeat 1: Zoé ($2 in chips)\n";
+
+  fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+    text
+      .encode_utf16()
+      .flat_map(|c| {
+        if big_endian {
+          c.to_be_bytes()
+        } else {
+          c.to_le_bytes()
+        }
+      })
+      .collect()
+  }
+
+  #[test]
+  fn decode_boms() {
+    let mut utf8 = b"\xef\xbb\xbf".to_vec();
+    utf8.extend(TEXT.as_bytes());
+    assert_eq!(decode(&utf8), TEXT);
+
+    let mut utf16le = vec![0xff, 0xfe];
+    utf16le.extend(utf16(TEXT, false));
+    assert_eq!(decode(&utf16le), TEXT);
+
+    let mut utf16be = vec![0xfe, 0xff];
+    utf16be.extend(utf16(TEXT, true));
+    assert_eq!(decode(&utf16be), TEXT);
+  }
+
+  #[test]
+  fn decode_without_bom() {
+    assert_eq!(decode(TEXT.as_bytes()), TEXT);
+    assert_eq!(decode(&utf16(TEXT, false)), TEXT);
+    assert_eq!(decode(&utf16(TEXT, true)), TEXT);
+  }
+
+  #[test]
+  fn decode_windows_1252() {
+    let bytes = b"Seat 1: Zo\xe9 ($2 in chips) \x80";
+    assert_eq!(decode(bytes), "Seat 1: Zoé ($2 in chips) €");
+  }
+
+  #[test]
+  fn normalize_borrows_clean_text() {
+    assert!(matches!(normalize(TEXT), Cow::Borrowed(_)));
+    let dirty = format!("\u{feff}{}", TEXT.replace('\n', "\r\n"));
+    assert_eq!(normalize(&dirty), TEXT);
+  }
+
+  #[test]
+  fn read_archives() {
+    let dir = std::env::temp_dir().join(format!("input-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let gz_path = dir.join("hands.txt.gz");
+    let mut encoder = flate2::write::GzEncoder::new(
+      File::create(&gz_path).unwrap(),
+      flate2::Compression::default(),
+    );
+    encoder.write_all(TEXT.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    let sources = read_path(&gz_path).unwrap();
+    assert_eq!(sources.len(), 1);
+    assert!(sources[0].name.ends_with("hands.txt"));
+    assert_eq!(sources[0].content, TEXT);
+
+    let zip_path = dir.join("hands.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+    let options = zip::write::FileOptions::default();
+    zip.start_file("a.txt", options).unwrap();
+    zip.write_all(TEXT.as_bytes()).unwrap();
+    zip.add_directory("sub", options).unwrap();
+    zip.start_file("sub/b.txt", options).unwrap();
+    zip.write_all(&utf16(TEXT, false)).unwrap();
+    zip.finish().unwrap();
+    let sources = read_path(&zip_path).unwrap();
+    assert_eq!(sources.len(), 2);
+    assert!(sources[0].name.ends_with("hands.zip:a.txt"));
+    assert!(sources[1].name.ends_with("hands.zip:sub/b.txt"));
+    assert!(sources.iter().all(|s| s.content == TEXT));
+
+    assert!(read_path(&dir.join("missing.txt")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
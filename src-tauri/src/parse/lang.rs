@@ -0,0 +1,376 @@
+use std::fmt;
+
+/// Language of the client that wrote the history
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+  #[default]
+  English,
+  French,
+  German,
+  Spanish,
+  Portuguese,
+  Italian,
+}
+
+impl Language {
+  pub fn lang(&self) -> &'static Lang {
+    match self {
+      Language::English => &ENGLISH,
+      Language::French => &FRENCH,
+      Language::German => &GERMAN,
+      Language::Spanish => &SPANISH,
+      Language::Portuguese => &PORTUGUESE,
+      Language::Italian => &ITALIAN,
+    }
+  }
+}
+
+impl fmt::Display for Language {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Language::English => write!(f, "en"),
+      Language::French => write!(f, "fr"),
+      Language::German => write!(f, "de"),
+      Language::Spanish => write!(f, "es"),
+      Language::Portuguese => write!(f, "pt"),
+      Language::Italian => write!(f, "it"),
+    }
+  }
+}
+
+/// Kind of action, independent of the language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionKind {
+  Call,
+  Bet,
+  Raise,
+  Check,
+  Fold,
+}
+
+/// Words written by the client for a language.
+/// Street markers are matched at the start of the line, the action words at
+/// the start of the text following the player name.
+pub struct Lang {
+  pub language: Language,
+  pub seat: &'static str,      // "Seat 1: name (...)"
+  pub separator: &'static str, // "name: folds", "name : se couche" in French
+  pub small_blind: &'static str,
+  pub big_blind: &'static str,
+  pub hole_cards: &'static str,
+  pub flop: &'static str,
+  pub turn: &'static str,
+  pub river: &'static str,
+  pub show_down: &'static str,
+  pub summary: &'static str,
+  pub first: &'static str,  // "*** FIRST FLOP ***" when the hand is run twice
+  pub second: &'static str, // "*** SECOND FLOP ***"
+  pub dealt_to: &'static str,
+  pub calls: &'static str,
+  pub bets: &'static str,
+  pub raises: &'static str,
+  pub checks: &'static str,
+  pub folds: &'static str,
+  pub all_in: &'static str,
+  pub leaves: &'static str, // "name leaves the table", without colon
+  pub uncalled: &'static str,
+  pub returned_to: &'static str, // "Uncalled bet ($1) returned to name"
+  pub collected: &'static str,   // "name collected $1 from pot"
+  pub shows: &'static str,
+  // table events, the text following the player name
+  pub sitting_out: &'static str, // "name is sitting out"
+  pub sits_out: &'static str,    // "name: sits out"
+  pub timed_out: &'static str,   // "name has timed out while disconnected"
+  pub disconnected: &'static str,
+  pub connected: &'static str,
+  pub returned: &'static str,
+  pub joins: &'static str, // "name joins the table at seat #3"
+  pub said: &'static str,  // "name said, \"hello\""
+  pub cashed_out: &'static str,
+}
+
+// The English words come from real histories. The other languages were
+// translated from them and still have to be checked against the histories of
+// a client set to each language, test/hands_{fr,de,es,pt,it}.txt are the same
+// translations.
+pub static ENGLISH: Lang = Lang {
+  language: Language::English,
+  seat: "Seat ",
+  separator: ":",
+  small_blind: "posts small blind",
+  big_blind: "posts big blind",
+  hole_cards: "*** HOLE CARDS ***",
+  flop: "*** FLOP ***",
+  turn: "*** TURN ***",
+  river: "*** RIVER ***",
+  show_down: "*** SHOW DOWN ***",
+  summary: "*** SUMMARY ***",
+  first: "FIRST ",
+  second: "SECOND ",
+  dealt_to: "Dealt to ",
+  calls: "calls",
+  bets: "bets",
+  raises: "raises",
+  checks: "checks",
+  folds: "folds",
+  all_in: "all-in",
+  leaves: " leaves the table",
+  uncalled: "Uncalled bet",
+  returned_to: " returned to ",
+  collected: " collected ",
+  shows: "shows",
+  sitting_out: " is sitting out",
+  sits_out: ": sits out",
+  timed_out: " has timed out",
+  disconnected: " is disconnected",
+  connected: " is connected",
+  returned: " has returned",
+  joins: " joins the table at seat #",
+  said: " said, \"",
+  cashed_out: " cashed out the hand for ",
+};
+
+pub static FRENCH: Lang = Lang {
+  language: Language::French,
+  seat: "Siège ",
+  separator: " :",
+  small_blind: "pose la petite blind",
+  big_blind: "pose la grosse blind",
+  hole_cards: "*** CARTES FERMÉES ***",
+  flop: "*** FLOP ***",
+  turn: "*** TOURNANT ***",
+  river: "*** RIVIÈRE ***",
+  show_down: "*** ABATTAGE ***",
+  summary: "*** RÉSUMÉ ***",
+  first: "PREMIER ",
+  second: "DEUXIÈME ",
+  dealt_to: "Distribuées à ",
+  calls: "suit",
+  bets: "mise",
+  raises: "relance",
+  checks: "passe",
+  folds: "se couche",
+  all_in: "tapis",
+  leaves: " quitte la table",
+  uncalled: "Mise non suivie",
+  returned_to: " retournée à ",
+  collected: " a remporté ",
+  shows: "montre",
+  sitting_out: " est absent",
+  sits_out: " : s'absente",
+  timed_out: " a dépassé le temps imparti",
+  disconnected: " est déconnecté",
+  connected: " est connecté",
+  returned: " est de retour",
+  joins: " rejoint la table au siège n°",
+  said: " a dit : \"",
+  cashed_out: " a encaissé la main pour ",
+};
+
+pub static GERMAN: Lang = Lang {
+  language: Language::German,
+  seat: "Platz ",
+  separator: ":",
+  small_blind: "setzt Small Blind",
+  big_blind: "setzt Big Blind",
+  hole_cards: "*** VERDECKTE KARTEN ***",
+  flop: "*** FLOP ***",
+  turn: "*** TURN ***",
+  river: "*** RIVER ***",
+  show_down: "*** SHOWDOWN ***",
+  summary: "*** ZUSAMMENFASSUNG ***",
+  first: "ERSTER ",
+  second: "ZWEITER ",
+  dealt_to: "Karten für ",
+  calls: "geht mit",
+  bets: "setzt",
+  raises: "erhöht",
+  checks: "checkt",
+  folds: "passt",
+  all_in: "all-in",
+  leaves: " verlässt den Tisch",
+  uncalled: "Nicht gecallter Einsatz",
+  returned_to: " zurück an ",
+  collected: " kassiert ",
+  shows: "zeigt",
+  sitting_out: " setzt aus",
+  sits_out: ": setzt aus",
+  timed_out: " hat die Zeit überschritten",
+  disconnected: " ist nicht verbunden",
+  connected: " ist verbunden",
+  returned: " ist zurück",
+  joins: " setzt sich an den Tisch auf Platz #",
+  said: " sagte: \"",
+  cashed_out: " hat die Hand ausgezahlt für ",
+};
+
+pub static SPANISH: Lang = Lang {
+  language: Language::Spanish,
+  seat: "Asiento ",
+  separator: ":",
+  small_blind: "pone la ciega pequeña",
+  big_blind: "pone la ciega grande",
+  hole_cards: "*** CARTAS DE MANO ***",
+  flop: "*** FLOP ***",
+  turn: "*** TURN ***",
+  river: "*** RIVER ***",
+  show_down: "*** ENSEÑAR CARTAS ***",
+  summary: "*** RESUMEN ***",
+  first: "PRIMER ",
+  second: "SEGUNDO ",
+  dealt_to: "Repartidas a ",
+  calls: "iguala",
+  bets: "apuesta",
+  raises: "sube",
+  checks: "pasa",
+  folds: "se retira",
+  all_in: "all-in",
+  leaves: " abandona la mesa",
+  uncalled: "Apuesta no igualada",
+  returned_to: " devuelta a ",
+  collected: " se llevó ",
+  shows: "muestra",
+  sitting_out: " está ausente",
+  sits_out: ": se ausenta",
+  timed_out: " ha agotado su tiempo",
+  disconnected: " está desconectado",
+  connected: " está conectado",
+  returned: " ha vuelto",
+  joins: " se une a la mesa en el asiento #",
+  said: " dijo: \"",
+  cashed_out: " cobró la mano por ",
+};
+
+pub static PORTUGUESE: Lang = Lang {
+  language: Language::Portuguese,
+  seat: "Lugar ",
+  separator: ":",
+  small_blind: "paga o small blind",
+  big_blind: "paga o big blind",
+  hole_cards: "*** CARTAS FECHADAS ***",
+  flop: "*** FLOP ***",
+  turn: "*** TURN ***",
+  river: "*** RIVER ***",
+  show_down: "*** MOSTRAR CARTAS ***",
+  summary: "*** RESUMO ***",
+  first: "PRIMEIRO ",
+  second: "SEGUNDO ",
+  dealt_to: "Cartas dadas a ",
+  calls: "paga",
+  bets: "aposta",
+  raises: "aumenta",
+  checks: "passa",
+  folds: "desiste",
+  all_in: "all-in",
+  leaves: " sai da mesa",
+  uncalled: "Aposta não coberta",
+  returned_to: " devolvida a ",
+  collected: " recebeu ",
+  shows: "mostra",
+  sitting_out: " está ausente",
+  sits_out: ": fica ausente",
+  timed_out: " esgotou o tempo",
+  disconnected: " está desconectado",
+  connected: " está conectado",
+  returned: " voltou",
+  joins: " entra na mesa no lugar #",
+  said: " disse: \"",
+  cashed_out: " retirou a mão por ",
+};
+
+pub static ITALIAN: Lang = Lang {
+  language: Language::Italian,
+  seat: "Posto ",
+  separator: ":",
+  small_blind: "posta lo small blind",
+  big_blind: "posta il big blind",
+  hole_cards: "*** CARTE COPERTE ***",
+  flop: "*** FLOP ***",
+  turn: "*** TURN ***",
+  river: "*** RIVER ***",
+  show_down: "*** SHOWDOWN ***",
+  summary: "*** RIEPILOGO ***",
+  first: "PRIMO ",
+  second: "SECONDO ",
+  dealt_to: "Carte distribuite a ",
+  calls: "chiama",
+  bets: "punta",
+  raises: "rilancia",
+  checks: "fa check",
+  folds: "lascia",
+  all_in: "all-in",
+  leaves: " lascia il tavolo",
+  uncalled: "Puntata non chiamata",
+  returned_to: " restituita a ",
+  collected: " ha incassato ",
+  shows: "mostra",
+  sitting_out: " è assente",
+  sits_out: ": si assenta",
+  timed_out: " ha esaurito il tempo",
+  disconnected: " è disconnesso",
+  connected: " è connesso",
+  returned: " è tornato",
+  joins: " si siede al tavolo al posto #",
+  said: " ha detto: \"",
+  cashed_out: " ha incassato la mano per ",
+};
+
+static LANGS: [&Lang; 6] = [&ENGLISH, &FRENCH, &GERMAN, &SPANISH, &PORTUGUESE, &ITALIAN];
+
+/// Guess the language of a hand from the words of the blinds, street markers
+/// and dealt line. English wins the ties.
+pub fn detect(hand_txt: &str) -> Language {
+  let mut best = (0, Language::English);
+  for lang in LANGS {
+    let score = [
+      lang.small_blind,
+      lang.big_blind,
+      lang.hole_cards,
+      lang.summary,
+      lang.dealt_to,
+    ]
+    .iter()
+    .filter(|token| hand_txt.contains(*token))
+    .count();
+    if score > best.0 {
+      best = (score, lang.language);
+    }
+  }
+  best.1
+}
+
+/// Line starting the summary, in any language
+pub fn is_summary(line: &str) -> bool {
+  LANGS.iter().any(|lang| line.starts_with(lang.summary))
+}
+
+impl Lang {
+  /// Run of a street marker. "*** FLOP ***" and "*** FIRST FLOP ***" deal the
+  /// first board, "*** SECOND FLOP ***" the second one of a hand run twice.
+  pub fn street_run(&self, line: &str, street: &str) -> Option<usize> {
+    let name = street.strip_prefix("*** ")?;
+    let marker = line.strip_prefix("*** ")?;
+    [("", 0), (self.first, 0), (self.second, 1)]
+      .into_iter()
+      .find(|(run, _)| {
+        marker
+          .strip_prefix(run)
+          .map_or(false, |m| m.starts_with(name))
+      })
+      .map(|(_, run)| run)
+  }
+
+  /// Kind of the action starting the text, the text following the player name
+  pub fn action_kind(&self, text: &str) -> Option<ActionKind> {
+    [
+      (self.calls, ActionKind::Call),
+      (self.bets, ActionKind::Bet),
+      (self.raises, ActionKind::Raise),
+      (self.checks, ActionKind::Check),
+      (self.folds, ActionKind::Fold),
+    ]
+    .into_iter()
+    .find(|(token, _)| text.starts_with(token))
+    .map(|(_, kind)| kind)
+  }
+}
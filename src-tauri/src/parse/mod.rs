@@ -1,17 +1,29 @@
-use std::fs;
+use std::io::BufRead;
+use std::path::Path;
 use std::str::Lines;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
+mod diagnostic;
 mod error;
 mod hand;
+pub mod input;
+pub mod lang;
+mod profile;
 mod re;
+pub mod split;
 mod start;
+#[cfg(test)]
+mod tests;
 
-use error::{ParseError, ParseErrorType};
-pub use hand::{Action, Blind, End, HandDetail, Player};
+pub use diagnostic::{write_quarantine, Diagnostic, LenientReport};
+pub use error::{ParseError, ParseErrorType};
+pub use hand::{
+  Action, Blind, Currency, End, EventKind, GameFormat, HandDetail, Player, TableEvent,
+};
+pub use lang::Language;
+pub use profile::Profile;
 
-use crate::db::schema::action::amount1;
+use lang::ActionKind;
 
 trait Dollar {
   fn round_two_digit(self) -> f32;
@@ -23,77 +35,68 @@ impl Dollar for f32 {
   }
 }
 
-pub fn parse_file(filepath: &str) -> Result<Vec<HandDetail>, ParseError> {
-  let mut filecontent = fs::read_to_string(filepath).expect("Invalid file");
-  filecontent = filecontent.replace('\r', "");
-  filecontent = filecontent.replace('\u{feff}', "");
+/// Parse every hand of the file, keeping going when a hand fails.
+/// The failing hands are reported as diagnostics instead of aborting the file.
+pub fn parse_file_lenient(filepath: &str) -> Result<LenientReport, ParseError> {
+  let mut report = LenientReport::default();
+  for source in input::read_path(Path::new(filepath))? {
+    let source_report = parse_str_lenient(&source.content, &source.name);
+    report.hands.extend(source_report.hands);
+    report.diagnostics.extend(source_report.diagnostics);
+  }
+  Ok(report)
+}
 
-  let start_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
-  let preflop_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
-  let flop_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
-  let turn_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
-  let river_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
-  let showdown_time: Arc<Mutex<Duration>> = Arc::new(Mutex::new(Duration::new(0, 0)));
+/// Parse a single hand, as stored in the `content` column of the database
+pub fn parse_hand(content: &str) -> Result<HandDetail, ParseError> {
+  let content = input::normalize(content);
+  HandDetail::parse_hand(content.trim(), &mut Profile::default())
+}
 
-  let hands_content = split_hands_content(&filecontent);
-  let mut hands: Vec<HandDetail> = vec![];
-  for hand in hands_content {
-    let h = HandDetail::parse_hand(
-      &hand,
-      &start_time,
-      &preflop_time,
-      &flop_time,
-      &turn_time,
-      &river_time,
-      &showdown_time,
-    )?;
-    hands.push(h);
-  }
+/// Parse the histories read from any stream, a pipe or a download. The
+/// encoding is detected from the bytes, see `input::decode`.
+pub fn parse_reader<R: BufRead>(mut reader: R) -> Result<Vec<HandDetail>, ParseError> {
+  let mut bytes = Vec::new();
+  reader
+    .read_to_end(&mut bytes)
+    .map_err(|e| ParseError::err(ParseErrorType::File, e))?;
+  parse_bytes(&bytes)
+}
 
-  println!("Start time : {}", start_time.lock().unwrap().as_millis());
-  println!(
-    "preflop time : {}",
-    preflop_time.lock().unwrap().as_millis()
-  );
-  println!("flop time : {}", flop_time.lock().unwrap().as_millis());
-  println!("turn time : {}", turn_time.lock().unwrap().as_millis());
-  println!("river time : {}", river_time.lock().unwrap().as_millis());
-  println!(
-    "showdown time : {}",
-    showdown_time.lock().unwrap().as_millis()
-  );
+pub fn parse_bytes(bytes: &[u8]) -> Result<Vec<HandDetail>, ParseError> {
+  parse_str(&input::decode(bytes))
+}
 
+/// Parse every complete hand, failing on the first broken one
+pub fn parse_str(content: &str) -> Result<Vec<HandDetail>, ParseError> {
+  let content = input::normalize(content);
+  let mut hands: Vec<HandDetail> = vec![];
+  // the client may be writing the last hand
+  for hand in split::split(&content).filter(|hand| !hand.is_truncated()) {
+    hands.push(HandDetail::parse_hand(hand.text, &mut Profile::default())?);
+  }
   Ok(hands)
 }
 
-fn split_hands_content(content: &str) -> Vec<String> {
-  let mut current_hand = String::new();
-  let mut hands = Vec::new();
-  for line in content.lines() {
-    if line.starts_with("PokerStars ") && !current_hand.is_empty() {
-      hands.push(current_hand);
-      current_hand = String::new();
+/// Lenient version of `parse_str`. `source` names the content in the
+/// diagnostics, usually the file it comes from.
+pub fn parse_str_lenient(content: &str, source: &str) -> LenientReport {
+  let content = input::normalize(content);
+  let mut profile = Profile::default();
+  let mut report = LenientReport::default();
+  for hand in split::split(&content).filter(|hand| !hand.is_truncated()) {
+    match HandDetail::parse_hand(hand.text, &mut profile) {
+      Ok(h) => report.hands.push(h),
+      Err(error) => report
+        .diagnostics
+        .push(Diagnostic::new(source, &hand, error)),
     }
-
-    current_hand.push_str(line);
-    current_hand.push('\n');
   }
-  if !current_hand.is_empty() {
-    hands.push(current_hand);
-  }
-  hands
+  report
 }
 
 impl HandDetail {
-  fn parse_hand(
-    hand_txt: &str,
-    start_time: &Arc<Mutex<Duration>>,
-    preflop_time: &Arc<Mutex<Duration>>,
-    flop_time: &Arc<Mutex<Duration>>,
-    turn_time: &Arc<Mutex<Duration>>,
-    river_time: &Arc<Mutex<Duration>>,
-    showdown_time: &Arc<Mutex<Duration>>,
-  ) -> Result<Self, ParseError> {
+  pub(crate) fn parse_hand(hand_txt: &str, profile: &mut Profile) -> Result<Self, ParseError> {
     let mut hand = HandDetail {
       content: hand_txt.to_string(),
       ..Default::default()
@@ -102,53 +105,76 @@ impl HandDetail {
 
     let mut instant_start = Instant::now();
 
+    hand.language = lang::detect(hand_txt);
     start::start(&mut hand, &mut lines)?;
-    *start_time.lock().unwrap() += instant_start.elapsed();
+    profile.start += instant_start.elapsed();
     instant_start = Instant::now();
 
     let mut next;
     next = preflop(&mut hand, &mut lines)?;
-    *preflop_time.lock().unwrap() += instant_start.elapsed();
+    profile.preflop += instant_start.elapsed();
     instant_start = Instant::now();
 
     if next {
       next = flop(&mut hand, &mut lines)?;
-      *flop_time.lock().unwrap() += instant_start.elapsed();
+      profile.flop += instant_start.elapsed();
       instant_start = Instant::now();
     }
     if next {
       next = turn(&mut hand, &mut lines)?;
-      *turn_time.lock().unwrap() += instant_start.elapsed();
+      profile.turn += instant_start.elapsed();
       instant_start = Instant::now();
     }
     if next {
       next = river(&mut hand, &mut lines)?;
-      *river_time.lock().unwrap() += instant_start.elapsed();
+      profile.river += instant_start.elapsed();
       instant_start = Instant::now();
     }
     if next {
       showdown(&mut hand, &mut lines)?;
-      *showdown_time.lock().unwrap() += instant_start.elapsed();
+      profile.showdown += instant_start.elapsed();
     }
+    profile.hands += 1;
     Ok(hand)
   }
 
-  fn get_player(&self, name: &str) -> Result<Player, ParseError> {
-    let trimed_name = name.trim_end_matches(':');
-    for player in &self.players {
-      match player {
-        Some(player) => {
-          if player.name == trimed_name {
-            return Ok(player.clone());
-          }
-        }
-        None => continue,
-      }
-    }
-    Err(ParseError::err(
-      ParseErrorType::Unknown(String::from("get player")),
-      name,
-    ))
+  // Seated player starting the line and the text after the separator.
+  // Screen names can contain ':', '[', spaces or action words, so we look for
+  // the known names instead of splitting the line, the longest name wins when
+  // one is the prefix of another.
+  pub(crate) fn split_player<'a>(
+    &self,
+    line: &'a str,
+    separator: &str,
+  ) -> Option<(Player, &'a str)> {
+    self
+      .players
+      .iter()
+      .flatten()
+      .filter_map(|player| {
+        let text = line
+          .strip_prefix(player.name.as_str())?
+          .strip_prefix(separator)?;
+        Some((player, text))
+      })
+      .max_by_key(|(player, _)| player.name.len())
+      .map(|(player, text)| (player.clone(), text))
+  }
+
+  // Seated player ending the line, "Uncalled bet ($1) returned to name"
+  fn split_player_end<'a>(&self, line: &'a str, separator: &str) -> Option<(Player, &'a str)> {
+    self
+      .players
+      .iter()
+      .flatten()
+      .filter_map(|player| {
+        let text = line
+          .strip_suffix(player.name.as_str())?
+          .strip_suffix(separator)?;
+        Some((player, text))
+      })
+      .max_by_key(|(player, _)| player.name.len())
+      .map(|(player, text)| (player.clone(), text))
   }
 }
 
@@ -157,30 +183,22 @@ impl HandDetail {
 // ========================================
 
 fn preflop(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
-  let line = lines.next().unwrap();
-  let player_capture = re::DEALT
-    .captures(line)
+  let lang = hand.language.lang();
+  let line = lines.next().ok_or(ParseError::err(
+    ParseErrorType::Preflop,
+    "missing dealt line",
+  ))?;
+  let dealt = line
+    .strip_prefix(lang.dealt_to)
     .ok_or(ParseError::err(ParseErrorType::Preflop, "capture player"))?;
-  let mut player_chars = player_capture[0].chars();
-  player_chars
-    .next()
-    .ok_or(ParseError::err(ParseErrorType::Preflop, "player next"))?;
-  player_chars
-    .next()
-    .ok_or(ParseError::err(ParseErrorType::Preflop, "player next"))?;
-  player_chars
-    .next_back()
-    .ok_or(ParseError::err(ParseErrorType::Preflop, "player next"))?;
-
-  let player = hand
-    .get_player(player_chars.as_str().trim())
-    .map_err(|e| ParseError::err(ParseErrorType::Preflop, e))?;
-
-  // WARN: suppose the user can't have '['
-  let cards_capture = re::BRACKET
-    .captures(line)
-    .ok_or(ParseError::err(ParseErrorType::Preflop, "capture cards"))?;
-  let binding = cards_capture[0].replace(['[', ']'], "");
+
+  let (player, cards) = hand.split_player(dealt, " [").ok_or(ParseError::err_msg(
+    ParseErrorType::Preflop,
+    "capture player",
+    dealt,
+  ))?;
+
+  let binding = cards.replace(['[', ']'], "");
   let mut cards = binding.split_whitespace();
   let card1 = cards
     .next()
@@ -190,15 +208,18 @@ fn preflop(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError>
     .ok_or(ParseError::err(ParseErrorType::Preflop, "card 2"))?;
 
   hand.players_card[player.position as usize - 1] = Some([card1.to_string(), card2.to_string()]);
+  hand.hero = Some(player);
 
   for line in lines {
-    if line.starts_with("*** SUMMARY ***") {
+    if line.starts_with(lang.summary) {
       return Ok(false);
     }
-    if line.starts_with("*** FLOP ***") {
+    if let Some(run) = lang.street_run(line, lang.flop) {
+      deal_board(hand, line, run);
       // 500us
-      // TODO : check error of regex, fuck it for now
-      let capture_card = &re::BRACKET.captures(line).unwrap();
+      let capture_card = &re::BRACKET
+        .captures(line)
+        .ok_or(ParseError::err(ParseErrorType::Preflop, "capture board"))?;
       let binding = capture_card[0].replace(['[', ']'], "");
       let mut cards = binding.split_whitespace();
       let card1 = cards
@@ -214,26 +235,35 @@ fn preflop(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError>
       return Ok(true);
     }
 
-    if Action::is_action(line) {
+    if Action::is_action(hand, line) {
       // 4 ms
-      hand.preflop.push(
-        Action::get_action(hand, line)
-          .unwrap_or_else(|e| panic!("Error in preflop action parsing : {}", e)),
-      );
-    } else if line.contains("collected") {
+      hand
+        .preflop
+        .push(Action::get_action(hand, line).map_err(|e| {
+          ParseError::err_msg(
+            ParseErrorType::Preflop,
+            e,
+            &format!("action in line {}", line),
+          )
+        })?);
+    } else if End::is_end(hand, line) {
       // 1ms
-      hand.end = End::extract_end(hand, line)?;
+      collect(hand, line, 0)?;
+    } else if let Some(event) = TableEvent::extract(hand, line, "preflop") {
+      hand.events.push(event);
     }
   }
   Ok(false)
 }
 
 fn flop(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
+  let lang = hand.language.lang();
   for line in lines {
-    if line.starts_with("*** SUMMARY ***") {
+    if line.starts_with(lang.summary) {
       return Ok(false);
     }
-    if line.starts_with("*** TURN ***") {
+    if let Some(run) = lang.street_run(line, lang.turn) {
+      deal_board(hand, line, run);
       let mut capture_card = re::BRACKET.captures_iter(line);
       capture_card.next();
       hand.turn_card = Some(
@@ -247,24 +277,27 @@ fn flop(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
       return Ok(true);
     }
 
-    if Action::is_action(line) {
-      hand.flop.push(
-        Action::get_action(hand, line)
-          .unwrap_or_else(|e| panic!("Error in flop action parsing : {}", e)),
-      );
-    } else if line.contains("collected") {
-      hand.end = End::extract_end(hand, line)?;
+    if Action::is_action(hand, line) {
+      hand.flop.push(Action::get_action(hand, line).map_err(|e| {
+        ParseError::err_msg(ParseErrorType::Flop, e, &format!("action in line {}", line))
+      })?);
+    } else if End::is_end(hand, line) {
+      collect(hand, line, 0)?;
+    } else if let Some(event) = TableEvent::extract(hand, line, "flop") {
+      hand.events.push(event);
     }
   }
   Ok(false)
 }
 
 fn turn(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
+  let lang = hand.language.lang();
   for line in lines {
-    if line.starts_with("*** SUMMARY ***") {
+    if line.starts_with(lang.summary) {
       return Ok(false);
     }
-    if line.starts_with("*** RIVER ***") {
+    if let Some(run) = lang.street_run(line, lang.river) {
+      deal_board(hand, line, run);
       let mut capture_card = re::BRACKET.captures_iter(line);
       capture_card.next();
       hand.river_card = Some(
@@ -277,212 +310,276 @@ fn turn(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
       );
       return Ok(true);
     }
-    if Action::is_action(line) {
+    if Action::is_action(hand, line) {
       hand.turn.push(Action::get_action(hand, line).map_err(|e| {
-        ParseError::err_msg(
-          ParseErrorType::River,
-          e,
-          &format!("action in line {}", line),
-        )
+        ParseError::err_msg(ParseErrorType::Turn, e, &format!("action in line {}", line))
       })?);
-    } else if line.contains("collected") {
-      hand.end = End::extract_end(hand, line)?; // FIXME : error
+    } else if End::is_end(hand, line) {
+      collect(hand, line, 0)?;
+    } else if let Some(event) = TableEvent::extract(hand, line, "turn") {
+      hand.events.push(event);
     }
   }
   Ok(false)
 }
 
 fn river(hand: &mut HandDetail, lines: &mut Lines) -> Result<bool, ParseError> {
+  let lang = hand.language.lang();
   for line in lines {
-    if line.starts_with("*** SUMMARY ***") {
+    if line.starts_with(lang.summary) {
       return Ok(false);
     }
-    if line.starts_with("*** SHOW DOWN ***") {
+    if lang.street_run(line, lang.show_down).is_some() {
+      hand.showdown = true;
       return Ok(true);
     }
-    if Action::is_action(line) {
-      hand.river.push(
-        Action::get_action(hand, line)
-          .unwrap_or_else(|e| panic! {"Error in river action parsing : {}", e}),
-      );
-    } else if line.contains("collected") {
-      hand.end = End::extract_end(hand, line)?;
+    if second_run(hand, line) {
+      continue;
+    }
+    if Action::is_action(hand, line) {
+      hand.river.push(Action::get_action(hand, line).map_err(|e| {
+        ParseError::err_msg(
+          ParseErrorType::River,
+          e,
+          &format!("action in line {}", line),
+        )
+      })?);
+    } else if End::is_end(hand, line) {
+      collect(hand, line, 0)?;
+    } else if let Some(event) = TableEvent::extract(hand, line, "river") {
+      hand.events.push(event);
     }
   }
   Ok(false)
 }
 
 fn showdown(hand: &mut HandDetail, lines: &mut Lines) -> Result<(), ParseError> {
+  let lang = hand.language.lang();
+  let mut run = 0;
   for line in lines {
-    if line.starts_with("*** SUMMARY ***") {
+    if line.starts_with(lang.summary) {
       return Ok(());
     }
+    // "*** SECOND SHOW DOWN ***", the next pots are won on the second board
+    if let Some(show_down_run) = lang.street_run(line, lang.show_down) {
+      run = show_down_run;
+      continue;
+    }
     // NOTE: ignore muck
-    if line.contains("shows") {
-      let player_capture = re::BEFORE_COLON.captures(line).ok_or(ParseError::err(
-        ParseErrorType::Showdown,
-        "player not found",
-      ))?;
-
-      // NOTE : suppose the username can't contains ':'
-      let player_name = player_capture[0].replace([':'], "");
-      let player = hand
-        .get_player(&player_name)
-        .map_err(|e| ParseError::err(ParseErrorType::Showdown, e))?;
-
+    let shown = hand
+      .split_player(line, lang.separator)
+      .filter(|(_, text)| text.trim_start().starts_with(lang.shows));
+    if let Some((player, text)) = shown {
       let capture_card = re::BRACKET
-        .captures(line)
+        .captures(text)
         .ok_or(ParseError::err(ParseErrorType::Showdown, "cards not found"))?;
       let cards_str = capture_card[0].replace(['[', ']'], "");
       let mut cards = cards_str.split_whitespace();
-      hand.players_card[player.position as usize - 1] = Some([
-        cards.next().unwrap().to_string(),
-        cards.next().unwrap().to_string(),
-      ]);
-    } else if line.contains("collected") {
-      hand.end = End::extract_end(hand, line)?;
+      let card1 = cards
+        .next()
+        .ok_or(ParseError::err(ParseErrorType::Showdown, "card 1"))?;
+      let card2 = cards
+        .next()
+        .ok_or(ParseError::err(ParseErrorType::Showdown, "card 2"))?;
+      hand.players_card[player.position as usize - 1] =
+        Some([card1.to_string(), card2.to_string()]);
+    } else if End::is_end(hand, line) {
+      collect(hand, line, run)?;
+    } else if let Some(event) = TableEvent::extract(hand, line, "showdown") {
+      hand.events.push(event);
     }
   }
   Ok(())
 }
 
+// Cards of a board line, "*** FIRST RIVER *** [7c 8d 9h 2s] [3d]" gives the
+// whole board of the first run
+fn deal_board(hand: &mut HandDetail, line: &str, run: usize) {
+  let cards = re::BRACKET
+    .find_iter(line)
+    .flat_map(|cards| cards.as_str().trim_matches(['[', ']']).split_whitespace())
+    .map(String::from)
+    .collect();
+  hand.board_mut(run).cards = cards;
+}
+
+// Boards of the second run are dealt once the first run is complete, after
+// the last actions, "*** SECOND FLOP ***", "*** SECOND TURN ***", ...
+fn second_run(hand: &mut HandDetail, line: &str) -> bool {
+  let lang = hand.language.lang();
+  let second = [lang.flop, lang.turn, lang.river]
+    .iter()
+    .any(|street| lang.street_run(line, street) == Some(1));
+  if second {
+    deal_board(hand, line, 1);
+  }
+  second
+}
+
+// "name collected $1 from pot", a winner of the board of the run
+fn collect(hand: &mut HandDetail, line: &str, run: usize) -> Result<(), ParseError> {
+  let end = End::extract_end(hand, line)?;
+  hand
+    .board_mut(run)
+    .winners
+    .push((end.winner.clone(), end.pot));
+  hand.end = end;
+  Ok(())
+}
+
+/// Read an amount as written by the client, "$0.06", "0,06 €", "(100)"
+pub(crate) fn parse_amount(amount: &str) -> Result<f32, ParseError> {
+  amount
+    .replace(['(', ')', ' '], "")
+    .replace(re::CURRENCY_SYMBOLS, "")
+    .replace(',', ".")
+    .parse::<f32>()
+    .map(|a| a.round_two_digit())
+    .map_err(|e| ParseError::err_msg(ParseErrorType::Unknown("amount".to_string()), e, amount))
+}
+
 impl End {
+  fn is_end(hand: &HandDetail, line: &str) -> bool {
+    hand
+      .split_player(line, hand.language.lang().collected)
+      .is_some()
+  }
+
   fn extract_end(hand: &HandDetail, line: &str) -> Result<Self, ParseError> {
-    let player_capture = &re::BEFORE_COLLECTED.captures(line).unwrap();
-    let mut words = player_capture[0].split_whitespace();
-    words.next_back().unwrap();
-    let player_name = words.collect::<Vec<&str>>().join(" ");
-    let winner = hand.get_player(&player_name)?;
+    let (winner, after) = hand
+      .split_player(line, hand.language.lang().collected)
+      .ok_or(ParseError::err(
+        ParseErrorType::Unknown("End".to_string()),
+        "winner capture",
+      ))?;
 
-    let pot_capture = re::AFTER_COLLECTED.captures(line).ok_or(ParseError::err(
+    let pot_capture = re::MONEY.captures(after).ok_or(ParseError::err(
       ParseErrorType::Unknown("End".to_string()),
       "pot capture",
     ))?;
-    let binding = pot_capture[0].replace(['$'], "");
-    let pot_str = binding.split_whitespace().nth(1).ok_or(ParseError::err(
-      ParseErrorType::Unknown("End".to_string()),
-      "error in pot reading",
-    ))?;
-    let pot = pot_str
-      .parse::<f32>()
-      .map(|p| p.round_two_digit())
-      .map_err(|e| {
-        ParseError::err_msg(
-          ParseErrorType::Unknown("End".to_string()),
-          e,
-          &format!("pot {}", pot_str),
-        )
-      })?;
+    let pot = parse_amount(&pot_capture[0])?;
     Ok(End { pot, winner })
   }
 }
 
+impl TableEvent {
+  pub(crate) fn extract(hand: &HandDetail, line: &str, moment: &'static str) -> Option<Self> {
+    let lang = hand.language.lang();
+    let kinds = [
+      (lang.sitting_out, EventKind::SitOut),
+      (lang.sits_out, EventKind::SitOut),
+      (lang.timed_out, EventKind::TimedOut),
+      (lang.disconnected, EventKind::Disconnected),
+      (lang.connected, EventKind::Connected),
+      (lang.returned, EventKind::Return),
+      (lang.joins, EventKind::Join(0)),
+      (lang.said, EventKind::Chat(String::new())),
+      (lang.cashed_out, EventKind::CashOut(0.)),
+    ];
+    // fill the kind with the text following the token
+    let event_kind = |kind: &EventKind, text: &str| match kind {
+      EventKind::Join(_) => text.trim().parse::<u8>().ok().map(EventKind::Join),
+      EventKind::Chat(_) => Some(EventKind::Chat(
+        text.strip_suffix('"').unwrap_or(text).to_string(),
+      )),
+      EventKind::CashOut(_) => {
+        let capture = re::MONEY.captures(text)?;
+        parse_amount(&capture[0]).ok().map(EventKind::CashOut)
+      }
+      // "has timed out while disconnected"
+      EventKind::TimedOut => Some(EventKind::TimedOut),
+      kind if text.is_empty() => Some(kind.clone()),
+      _ => None,
+    };
+
+    // the seated players first, the players joining the table aren't seated yet
+    let seated = kinds.iter().find_map(|(token, kind)| {
+      let (player, text) = hand.split_player(line, token)?;
+      Some((player.name, event_kind(kind, text)?))
+    });
+    let (player, kind) = seated.or_else(|| {
+      kinds.iter().find_map(|(token, kind)| {
+        let (player, text) = line.split_once(token)?;
+        Some((player.to_string(), event_kind(kind, text)?))
+      })
+    })?;
+    Some(TableEvent {
+      player,
+      moment,
+      kind,
+    })
+  }
+}
+
 impl Action {
-  fn is_action(line: &str) -> bool {
-    line.contains("calls")
-      || line.contains("bets")
-      || line.contains("raises")
-      || line.contains("check")
-      || line.contains("folds")
-      || line.contains("leaves")
-      || line.contains("Uncalled bet")
+  fn is_action(hand: &HandDetail, line: &str) -> bool {
+    let lang = hand.language.lang();
+    // "name: setzt aus" sits out in German, "setzt" is also a bet
+    if matches!(hand.split_player(line, lang.sits_out), Some((_, ""))) {
+      return false;
+    }
+    match hand.split_player(line, lang.separator) {
+      Some((_, text)) if lang.action_kind(text.trim_start()).is_some() => true,
+      _ => {
+        line.starts_with(lang.uncalled)
+          || matches!(hand.split_player(line, lang.leaves), Some((_, "")))
+      }
+    }
   }
 
   // need a special treatement
   fn get_uncalled(hand: &HandDetail, line: &str) -> Result<Self, ParseError> {
+    let lang = hand.language.lang();
     let capture = re::MONEY_BRACED.captures(line).ok_or(ParseError::err(
       ParseErrorType::Unknown("get uncalled".to_string()),
       "Can't find amount in uncalled",
     ))?;
-    let amount = capture[0]
-      .replace(['(', ')', '$'], "")
-      .parse::<f32>()
-      .map(|p| p.round_two_digit())
-      .map_err(|e| {
-        ParseError::err_msg(
-          ParseErrorType::Unknown("get uncalled".to_string()),
-          e,
-          &format!("amount : '{}'", &capture[0]),
-        )
-      })?;
-    let player = line
-      .split_whitespace()
-      .skip(5)
-      .collect::<Vec<&str>>()
-      .join(" ");
-    Ok(Action::UncalledBet(hand.get_player(&player)?, amount))
+    let amount = parse_amount(&capture[0])?;
+    let (player, _) = hand
+      .split_player_end(line, lang.returned_to)
+      .ok_or(ParseError::err(
+        ParseErrorType::Unknown("get uncalled".to_string()),
+        "Can't find player in uncalled",
+      ))?;
+    Ok(Action::UncalledBet(player, amount))
   }
 
   fn get_action(hand: &HandDetail, line: &str) -> Result<Self, ParseError> {
-    if line.starts_with("Uncalled bet") {
-      return Action::get_uncalled(hand, line);
-    }
-
-    let capture_position = re::BEFORE_COLON.captures(line).ok_or(ParseError::err(
-      ParseErrorType::Unknown("get action".to_string()),
-      "player not found",
-    ))?;
-    let player = capture_position[0].replace([':'], "");
-
-    let capture_after = re::AFTER_COLON.captures(line).ok_or(ParseError::err(
-      ParseErrorType::Unknown("get action".to_string()),
-      "capture after",
-    ))?;
-    let binding = capture_after[0].replace([':'], "");
-    let line = &binding.trim();
+    let lang = hand.language.lang();
+    let (player, line) = match hand.split_player(line, lang.separator) {
+      Some((player, text)) => (player, text.trim()),
+      None => {
+        if let Some((player, "")) = hand.split_player(line, lang.leaves) {
+          return Ok(Action::Leave(player));
+        }
+        return Action::get_uncalled(hand, line);
+      }
+    };
 
-    let capture_action = re::WORD.captures(line).ok_or(ParseError::err(
+    let action = lang.action_kind(line).ok_or(ParseError::err(
       ParseErrorType::Unknown("get action".to_string()),
-      "capture action",
+      "unknown action",
     ))?;
-    let binding = capture_action[0].replace([':', '$'], "");
-    let action = binding.trim();
 
     let captures_amount = re::MONEY.captures_iter(line);
-    // FIXME shit
-    let amounts = {
-      let mut vec = Vec::new();
-      for cap in captures_amount {
-        let x = cap[0]
-          .replace(['$'], "")
-          .parse::<f32>()
-          .map(|p| p.round_two_digit())
-          .map_err(|e| ParseError::err(ParseErrorType::Unknown("get action".to_string()), e))?;
-        vec.push(x);
-      }
-      vec
+    let amounts: Vec<f32> = captures_amount
+      .map(|cap| parse_amount(&cap[0]))
+      .collect::<Result<Vec<f32>, ParseError>>()?;
+
+    let amount = |i: usize| {
+      amounts.get(i).copied().ok_or(ParseError::err(
+        ParseErrorType::Unknown("get action".to_string()),
+        "amount not found",
+      ))
     };
 
+    let allin = line.contains(lang.all_in);
     match action {
-      "calls" => Ok(Action::Call(
-        hand.get_player(&player)?,
-        *amounts.first().unwrap(),
-        line.contains("all-in"),
-      )),
-      "bets" => Ok(Action::Bet(
-        hand.get_player(&player)?,
-        *amounts.first().unwrap(),
-        line.contains("all-in"),
-      )),
-      "raises" => Ok(Action::Raise(
-        hand.get_player(&player)?,
-        *amounts.first().unwrap(),
-        *amounts.get(1).unwrap(),
-        line.contains("all-in"),
-      )),
-      "checks" => Ok(Action::Check(hand.get_player(&player)?)),
-      "folds" => Ok(Action::Fold(hand.get_player(&player)?)),
-      "leaves" => Ok(Action::Leave(hand.get_player(&player)?)),
-      // first is Uncalled
-      // TODO : add Uncalled action
-      "bet" => Ok(Action::UncalledBet(
-        hand.get_player(&player)?,
-        *amounts.first().unwrap(),
-      )),
-      _ => Err(ParseError::err(
-        ParseErrorType::Unknown("get action".to_string()),
-        "unknown action",
-      )),
+      ActionKind::Call => Ok(Action::Call(player, amount(0)?, allin)),
+      ActionKind::Bet => Ok(Action::Bet(player, amount(0)?, allin)),
+      ActionKind::Raise => Ok(Action::Raise(player, amount(0)?, amount(1)?, allin)),
+      ActionKind::Check => Ok(Action::Check(player)),
+      ActionKind::Fold => Ok(Action::Fold(player)),
     }
   }
 }
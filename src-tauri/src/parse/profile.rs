@@ -0,0 +1,84 @@
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Time spent in each phase of an import.
+/// Every worker fills its own profile, they are summed at the end so the
+/// timing never needs a lock.
+#[derive(Default, Debug, Clone)]
+pub struct Profile {
+  pub read: Duration, // reading, decompressing and decoding the files
+  pub split: Duration,
+  pub start: Duration,
+  pub preflop: Duration,
+  pub flop: Duration,
+  pub turn: Duration,
+  pub river: Duration,
+  pub showdown: Duration,
+  pub insert: Duration, // database writes
+  pub hands: usize,
+}
+
+impl Profile {
+  fn phases(&self) -> [(&'static str, Duration); 9] {
+    [
+      ("read", self.read),
+      ("split", self.split),
+      ("start", self.start),
+      ("preflop", self.preflop),
+      ("flop", self.flop),
+      ("turn", self.turn),
+      ("river", self.river),
+      ("showdown", self.showdown),
+      ("insert", self.insert),
+    ]
+  }
+
+  pub fn total(&self) -> Duration {
+    self.phases().iter().map(|(_, duration)| *duration).sum()
+  }
+}
+
+impl AddAssign<&Profile> for Profile {
+  fn add_assign(&mut self, other: &Profile) {
+    self.read += other.read;
+    self.split += other.split;
+    self.start += other.start;
+    self.preflop += other.preflop;
+    self.flop += other.flop;
+    self.turn += other.turn;
+    self.river += other.river;
+    self.showdown += other.showdown;
+    self.insert += other.insert;
+    self.hands += other.hands;
+  }
+}
+
+// Durations are cumulated over the workers, they can exceed the wall time
+impl fmt::Display for Profile {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let total = self.total().as_secs_f64();
+    writeln!(f, "{:<10}{:>12}{:>8}{:>14}", "phase", "ms", "%", "us/hand")?;
+    for (name, duration) in self.phases() {
+      let percent = if total == 0. {
+        0.
+      } else {
+        duration.as_secs_f64() / total * 100.
+      };
+      let per_hand = if self.hands == 0 {
+        0.
+      } else {
+        duration.as_secs_f64() * 1e6 / self.hands as f64
+      };
+      writeln!(
+        f,
+        "{:<10}{:>12}{:>7.1}%{:>14.1}",
+        name,
+        duration.as_millis(),
+        percent,
+        per_hand
+      )?;
+    }
+    writeln!(f, "{} hands, {} ms", self.hands, self.total().as_millis())
+  }
+}
@@ -1,19 +1,23 @@
 use regex::Regex;
 
+// symbols written before the amounts, play money has none
+pub const CURRENCY_SYMBOLS: [char; 4] = ['$', '€', '£', '₹'];
+
 lazy_static! {
-  pub static ref BEFORE_COLON: Regex = Regex::new(r".*:").unwrap();
-  pub static ref AFTER_COLON: Regex = Regex::new(r":.*").unwrap();
   pub static ref BRACKET: Regex = Regex::new(r"\[.+?\]").unwrap();
   pub static ref TABLE_NAME: Regex = Regex::new(r"'([^']*)'").unwrap();
-  pub static ref MONEY_BRACED: Regex = Regex::new(r"\(\$?(\d+\.)?\d+").unwrap();
-  pub static ref MONEY: Regex = Regex::new(r"\$?(\d+\.)?\d+").unwrap();
-  pub static ref LIMIT: Regex = Regex::new(r"\(\$?(\d+\.)?\d+\/\$?(\d+\.)?\d+( USD)?\)").unwrap();
-  pub static ref TABLE_ID: Regex = Regex::new(r"#(\d+)").unwrap();
+  // some languages write "0,01 €" instead of "€0.01"
+  pub static ref MONEY_BRACED: Regex = Regex::new(r"\([$€£₹]?\d+([.,]\d+)?").unwrap();
+  pub static ref MONEY: Regex = Regex::new(r"[$€£₹]?\d+([.,]\d+)?").unwrap();
+  pub static ref LIMIT: Regex = Regex::new(
+    r"\(([$€£₹])? ?(\d+(?:[.,]\d+)?) ?([$€£₹])?/[$€£₹]? ?(\d+(?:[.,]\d+)?) ?[$€£₹]?(?: ([A-Z]{3}))?\)"
+  )
+  .unwrap();
+  // "#123" or "n° 123"
+  pub static ref TABLE_ID: Regex = Regex::new(r"(?:#|n° ?)(\d+)").unwrap();
   pub static ref TABLE_SIZE: Regex = Regex::new(r"(\d+)-max").unwrap();
-  pub static ref DEALT: Regex = Regex::new(r"to .*\[").unwrap();
-  pub static ref WORD: Regex = Regex::new(r"\w+").unwrap();
-  pub static ref BEFORE_COLLECTED: Regex = Regex::new(r".* collected").unwrap();
-  pub static ref AFTER_COLLECTED: Regex = Regex::new(r"collected \$?(\d+\.)?\d+").unwrap();
-  pub static ref NUMBER: Regex = Regex::new(r"[0-9]").unwrap();
-  pub static ref AFTER_COLON_P: Regex = Regex::new(r": .* \(").unwrap();
+  pub static ref DATE: Regex =
+    Regex::new(r"(\d{4}/\d{1,2}/\d{1,2} \d{1,2}:\d{2}:\d{2}) ([A-Z]+)").unwrap();
+  pub static ref HAND_HEADER: Regex =
+    Regex::new(r"^\x{feff}?PokerStars [^#\n]*?(?:#|n° ?)\d+").unwrap();
 }
@@ -0,0 +1,172 @@
+use crate::parse::{lang, re};
+
+/// A hand found in the content of a history file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HandSlice<'a> {
+  pub index: usize,   // position of the hand in the content
+  pub offset: usize,  // byte offset of the header in the content given to `split`
+  pub text: &'a str,  // text of the hand, without the blank lines around it
+  pub complete: bool, // the summary has been written
+  pub last: bool,
+}
+
+impl HandSlice<'_> {
+  /// The last hand of a file still being written by the client
+  pub fn is_truncated(&self) -> bool {
+    self.last && !self.complete
+  }
+}
+
+/// Iterate over the hands of the content without copying them.
+/// A hand starts on any PokerStars header line ("PokerStars Hand #",
+/// "PokerStars Zoom Hand #", "PokerStars Home Game Hand #", "PokerStars Game #",
+/// tournaments, ...) and lasts until the next header, whatever the number of
+/// blank lines between them. Text before the first header is ignored.
+pub struct HandSplitter<'a> {
+  content: &'a str,
+  position: usize,
+  index: usize,
+}
+
+pub fn split(content: &str) -> HandSplitter<'_> {
+  HandSplitter {
+    content,
+    position: 0,
+    index: 0,
+  }
+}
+
+pub fn is_header(line: &str) -> bool {
+  re::HAND_HEADER.is_match(line)
+}
+
+impl<'a> HandSplitter<'a> {
+  // offset of the first header line starting at or after `from`, which must be
+  // the start of a line
+  fn find_header(&self, from: usize) -> Option<usize> {
+    let mut offset = from;
+    for line in self.content[from..].split_inclusive('\n') {
+      if is_header(line) {
+        return Some(offset);
+      }
+      offset += line.len();
+    }
+    None
+  }
+}
+
+impl<'a> Iterator for HandSplitter<'a> {
+  type Item = HandSlice<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let start = self.find_header(self.position)?;
+    let body = match self.content[start..].find('\n') {
+      Some(line_end) => start + line_end + 1,
+      None => self.content.len(),
+    };
+    let end = self.find_header(body).unwrap_or(self.content.len());
+    self.position = end;
+
+    let text = self.content[start..end].trim_end();
+    let slice = HandSlice {
+      index: self.index,
+      offset: start,
+      text,
+      complete: text.lines().any(lang::is_summary),
+      last: end == self.content.len(),
+    };
+    self.index += 1;
+    Some(slice)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SUMMARY: &str = "*** SUMMARY ***\nTotal pot $0.05 | Rake $0\n";
+
+  fn hand(header: &str) -> String {
+    format!(
+      "{}\nTable 'Aludra' 6-max Seat #1 is the button\n{}",
+      header, SUMMARY
+    )
+  }
+
+  fn headers(content: &str) -> Vec<&str> {
+    split(content)
+      .map(|hand| hand.text.lines().next().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn header_variants() {
+    let variants = [
+      "PokerStars Hand #249638850870:  Hold'em No Limit ($0.01/$0.02 USD) - 2024/03/26 22:02:04 ET",
+      "PokerStars Zoom Hand #249638850871:  Hold'em No Limit ($0.01/$0.02) - 2024/03/26 22:02:04 ET",
+      "PokerStars Home Game Hand #249638850872: {Club} Hold'em No Limit ($0.01/$0.02) - 2024/03/26 22:02:04 ET",
+      "PokerStars Game #249638850873: Tournament #3718569312, $1+$0.10 USD Hold'em No Limit - 2024/03/26 22:02:04 ET",
+      "PokerStars Hand #249638850874: Tournament #3718569312, Freeroll  Hold'em No Limit - Level I (10/20) - 2024/03/26 22:02:04 ET",
+      "\u{feff}PokerStars Main n° 249638850875:  Hold'em No Limit (0,01 €/0,02 € EUR) - 2024/03/26 22:02:04 HEC",
+    ];
+    let content: String = variants
+      .iter()
+      .map(|header| hand(header) + "\n\n")
+      .collect();
+    assert_eq!(headers(&content), variants);
+
+    // lines quoting a hand number aren't headers
+    assert!(!is_header("Table 'PokerStars Hand #1' 6-max"));
+    assert!(!is_header("PokerStars Hand: none"));
+  }
+
+  #[test]
+  fn blank_line_separators() {
+    let first = hand("PokerStars Hand #1: Hold'em No Limit");
+    let second = hand("PokerStars Hand #2: Hold'em No Limit");
+    for separator in ["", "\n", "\n\n", "\n\n\n\n\n", "\n  \n\t\n"] {
+      let content = format!("{}{}{}", first, separator, second);
+      let hands: Vec<HandSlice> = split(&content).collect();
+      assert_eq!(hands.len(), 2, "separator {:?}", separator);
+      assert_eq!(hands[0].text, first.trim_end());
+      assert_eq!(hands[1].text, second.trim_end());
+      assert_eq!(hands[1].offset, first.len() + separator.len());
+      assert_eq!(hands[1].index, 1);
+      assert!(hands.iter().all(|hand| hand.complete));
+    }
+
+    // text before the first header is skipped
+    let content = format!("Hand history exported\n\n{}", first);
+    let hands: Vec<HandSlice> = split(&content).collect();
+    assert_eq!(hands.len(), 1);
+    assert_eq!(&content[hands[0].offset..], first);
+    assert_eq!(split("").count(), 0);
+    assert_eq!(split("no hands here\n").count(), 0);
+  }
+
+  #[test]
+  fn trailing_incomplete_hands() {
+    let complete = hand("PokerStars Hand #1: Hold'em No Limit");
+    let partial = "PokerStars Hand #2: Hold'em No Limit\nTable 'Aludra' 6-max Seat #1 is the button\nSeat 1: a ($2 in chips)";
+
+    // the client is writing the last hand
+    let content = format!("{}\n\n{}", complete, partial);
+    let hands: Vec<HandSlice> = split(&content).collect();
+    assert_eq!(hands.len(), 2);
+    assert!(!hands[0].is_truncated());
+    assert!(hands[1].last && !hands[1].complete);
+    assert!(hands[1].is_truncated());
+
+    // a header alone at the end of the file
+    let content = format!("{}\n\nPokerStars Hand #3: Hold'em", complete);
+    let hands: Vec<HandSlice> = split(&content).collect();
+    assert_eq!(hands[1].text, "PokerStars Hand #3: Hold'em");
+    assert!(hands[1].is_truncated());
+
+    // a hand cut in the middle of the file is broken, not truncated
+    let content = format!("{}\n\n{}", partial, complete);
+    let hands: Vec<HandSlice> = split(&content).collect();
+    assert!(!hands[0].complete && !hands[0].is_truncated());
+    assert!(hands[1].last && !hands[1].is_truncated());
+  }
+}
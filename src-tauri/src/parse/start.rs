@@ -1,60 +1,149 @@
-use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
 use std::str::Lines;
 
-use crate::parse::re;
-use crate::parse::{Blind, HandDetail, ParseError, ParseErrorType, Player};
+use crate::parse::lang::Lang;
+use crate::parse::{parse_amount, re};
+use crate::parse::{
+  Blind, Currency, GameFormat, HandDetail, ParseError, ParseErrorType, Player, TableEvent,
+};
 
-fn extract_id(line: &str) -> Result<i64, ParseError> {
+pub fn extract_id(line: &str) -> Result<i64, ParseError> {
   let capture_id = re::TABLE_ID
     .captures(line)
     .ok_or(ParseError::err(ParseErrorType::Start, "Id regex failed"))?;
-  let mut chars = capture_id[0].chars();
-  chars.next();
-  let content = chars.as_str();
+  let content = &capture_id[1];
   content
     .parse::<i64>()
     .map_err(|e| ParseError::err_msg(ParseErrorType::Start, e, content))
 }
 
-fn extract_date(line: &str) -> Result<DateTime<FixedOffset>, ParseError> {
-  let capture_date = re::BRACKET
-    .captures(line)
-    .ok_or(ParseError::err(ParseErrorType::Start, "Date regex failed"))?;
-  let date_string = capture_date[0].to_string();
+fn extract_format(line: &str) -> GameFormat {
+  if line.starts_with("PokerStars Zoom ") {
+    GameFormat::Zoom
+  } else {
+    GameFormat::Cash
+  }
+}
 
-  let date = NaiveDateTime::parse_from_str(&date_string, "[%Y/%m/%d %H:%M:%S ET]")
-    .map_err(|e| ParseError::err(ParseErrorType::Start, e))?;
+/// Dates of the hand: the UTC time and the local time of the player.
+/// The header holds the time in the time zone of the player followed by the
+/// ET time in brackets, "2024/03/26 22:02:04 CET [2024/03/26 17:02:04 ET]",
+/// or a single time when the player is in ET or the client is set to one
+/// time zone.
+fn extract_dates(line: &str) -> Result<(DateTime<Utc>, Option<DateTime<FixedOffset>>), ParseError> {
+  let mut utc = None;
+  let mut local = None;
+  for capture in re::DATE.captures_iter(line) {
+    let naive = NaiveDateTime::parse_from_str(&capture[1], "%Y/%m/%d %H:%M:%S")
+      .map_err(|e| ParseError::err_msg(ParseErrorType::Start, e, &capture[0]))?;
+    let zone = &capture[2];
+    // a local zone we don't know, the ET time is enough
+    let date = match zoned_date(naive, zone) {
+      Some(date) => date,
+      None => continue,
+    };
 
-  let offset = FixedOffset::east_opt(5 * 3600)
-    .ok_or(ParseError::err(ParseErrorType::Start, "Offset failed"))?;
+    // the ET time is the reference, the other one is the local time
+    if zone == "ET" {
+      utc = Some(date.with_timezone(&Utc));
+    } else if local.is_none() {
+      local = Some(date);
+    }
+  }
 
-  Ok(DateTime::<FixedOffset>::from_naive_utc_and_offset(
-    date, offset,
-  ))
+  let utc = utc
+    .or(local.map(|date| date.with_timezone(&Utc)))
+    .ok_or(ParseError::err(
+      ParseErrorType::Start,
+      "No date with a known time zone",
+    ))?;
+  Ok((utc, local))
+}
+
+// Attach the zone to a time written by the client. "ET" and the other
+// North American zones follow the daylight saving rules, the other
+// abbreviations already tell if it's summer time.
+fn zoned_date(naive: NaiveDateTime, zone: &str) -> Option<DateTime<FixedOffset>> {
+  let tz = match zone {
+    "ET" => Some(chrono_tz::America::New_York),
+    "CT" => Some(chrono_tz::America::Chicago),
+    "MT" => Some(chrono_tz::America::Denver),
+    "PT" => Some(chrono_tz::America::Los_Angeles),
+    "AT" => Some(chrono_tz::America::Halifax),
+    _ => None,
+  };
+  if let Some(tz) = tz {
+    // during the fall back hour the time is ambiguous, keep the first one.
+    // A time in the spring forward gap can't be written by the client.
+    return tz
+      .from_local_datetime(&naive)
+      .earliest()
+      .map(|date| date.fixed_offset());
+  }
+
+  let hours = match zone {
+    "UTC" | "GMT" | "WET" => 0.,
+    "BST" | "WEST" | "CET" => 1.,
+    "CEST" | "EET" => 2.,
+    "EEST" | "MSK" => 3.,
+    "IST" => 5.5,
+    "AWST" => 8.,
+    "JST" => 9.,
+    "ACST" => 9.5,
+    "AEST" => 10.,
+    "ACDT" => 10.5,
+    "AEDT" => 11.,
+    "NZST" => 12.,
+    "NZDT" => 13.,
+    "ART" | "BRT" => -3.,
+    "EDT" => -4.,
+    "EST" | "CDT" => -5.,
+    "CST" | "MDT" => -6.,
+    "MST" | "PDT" => -7.,
+    "PST" => -8.,
+    _ => return None,
+  };
+  let offset = FixedOffset::east_opt((hours * 3600.) as i32)?;
+  offset.from_local_datetime(&naive).single()
+}
+
+// The code after the limits tells the currency, "($0.01/$0.02 USD)", older
+// histories only have the symbol, before or after the amount depending on the
+// language. Play money has neither, "(50/100)".
+fn extract_currency(line: &str) -> Result<Currency, ParseError> {
+  let capture_limits = re::LIMIT
+    .captures(line)
+    .ok_or(ParseError::err(ParseErrorType::Start, "Limit not found"))?;
+  if let Some(code) = capture_limits.get(5) {
+    return match code.as_str() {
+      "USD" => Ok(Currency::Usd),
+      "EUR" => Ok(Currency::Eur),
+      "GBP" => Ok(Currency::Gbp),
+      "CAD" => Ok(Currency::Cad),
+      "INR" => Ok(Currency::Inr),
+      other => Err(ParseError::err(
+        ParseErrorType::Start,
+        format!("unknown currency {}", other),
+      )),
+    };
+  }
+  let symbol = capture_limits.get(1).or(capture_limits.get(3));
+  match symbol.map(|symbol| symbol.as_str()) {
+    Some("$") => Ok(Currency::Usd),
+    Some("€") => Ok(Currency::Eur),
+    Some("£") => Ok(Currency::Gbp),
+    Some("₹") => Ok(Currency::Inr),
+    _ => Ok(Currency::PlayMoney),
+  }
 }
 
 fn extract_limits(line: &str) -> Result<(f32, f32), ParseError> {
-  // replace by simple number match and take 2nd and 3rd
-  let capture_limites = re::LIMIT
+  let capture_limits = re::LIMIT
     .captures(line)
     .ok_or(ParseError::err(ParseErrorType::Start, "Limit not found"))?;
-  let limits_str = capture_limites[0].to_string();
-  let mut chars = limits_str.chars();
-  chars.next();
-  chars.next_back();
-  let mut limits = chars.as_str().split('/');
-  let small_limit_str = limits.next().unwrap();
-  let big_limit_str = limits.next().unwrap();
   Ok((
-    small_limit_str
-      .replace('$', "")
-      .parse::<f32>()
-      .map_err(|e| ParseError::err(ParseErrorType::Start, e))?,
-    big_limit_str
-      .replace('$', "")
-      .replace(" USD", "")
-      .parse::<f32>()
-      .map_err(|e| ParseError::err(ParseErrorType::Start, e))?,
+    parse_amount(&capture_limits[2]).map_err(|e| ParseError::err(ParseErrorType::Start, e))?,
+    parse_amount(&capture_limits[4]).map_err(|e| ParseError::err(ParseErrorType::Start, e))?,
   ))
 }
 
@@ -70,20 +159,22 @@ fn extract_table_name(line: &str) -> Result<String, ParseError> {
 }
 
 fn extract_button_position(line: &str) -> Result<u8, ParseError> {
-  let capture_button_position = re::TABLE_ID.captures(line).ok_or(ParseError::err(
+  // skip the table name, it may contain a '#'
+  let after_name = line.rsplit_once('\'').map_or(line, |(_, after)| after);
+  let capture_button_position = re::TABLE_ID.captures(after_name).ok_or(ParseError::err(
     ParseErrorType::Start,
     "Button position regex failed",
   ))?;
-  let mut chars = capture_button_position[0].chars();
-  chars.next();
-  chars
-    .as_str()
+  capture_button_position[1]
     .parse::<u8>()
     .map_err(|e| ParseError::err(ParseErrorType::Start, e))
 }
 
 fn extract_table_size(line: &str) -> Result<u8, ParseError> {
-  let capture_table_size = re::TABLE_SIZE.captures(line).unwrap();
+  let capture_table_size = re::TABLE_SIZE.captures(line).ok_or(ParseError::err(
+    ParseErrorType::Start,
+    "Table size not found",
+  ))?;
   let mut chars = capture_table_size[0].chars();
   chars.next_back();
   chars.next_back();
@@ -95,55 +186,52 @@ fn extract_table_size(line: &str) -> Result<u8, ParseError> {
     .map_err(|e| ParseError::err(ParseErrorType::Start, e))
 }
 
-fn extract_blind(hand: &HandDetail, line: &str) -> Result<Blind, ParseError> {
-  let capture_player = re::BEFORE_COLON
-    .captures(line)
-    .ok_or(ParseError::err(ParseErrorType::Start, "extracting blind"))?;
-  let player = hand
-    .get_player(&capture_player[0].replace([':'], ""))
-    .map_err(|e| ParseError::err_msg(ParseErrorType::Start, e, "parsing player"))?;
-  let after_line = re::AFTER_COLON.captures(line).ok_or(ParseError::err(
-    ParseErrorType::Start,
-    "unable to get the line after colon",
-  ))?;
-  let line = after_line[0].to_string();
-  let capture = re::MONEY.captures(&line).ok_or(ParseError::err(
+// "name: posts small blind $0.01", the name is one of the seated players
+fn extract_blind(player: Player, after: &str) -> Result<Blind, ParseError> {
+  let capture = re::MONEY.captures(after).ok_or(ParseError::err(
     ParseErrorType::Start,
     "Can't find amount in blind",
   ))?;
-  let amount = capture[0]
-    .replace(['(', '$'], "")
-    .parse::<f32>()
-    .map_err(|e| {
-      ParseError::err_msg(
-        ParseErrorType::Start,
-        e,
-        &format!("parsing amount {}", &capture[0]),
-      )
-    })?;
+  let amount = parse_amount(&capture[0]).map_err(|e| {
+    ParseError::err_msg(
+      ParseErrorType::Start,
+      e,
+      &format!("parsing amount {}", &capture[0]),
+    )
+  })?;
   Ok(Blind { player, amount })
 }
 
-fn extract_seat(line: &str) -> Result<Player, ParseError> {
-  let capture_position = re::NUMBER
-    .captures(line)
+// "Seat 1: name ($2 in chips)", "Siège 1 : name (2 € en jetons)"
+fn extract_seat(lang: &Lang, line: &str, seats: usize) -> Result<Player, ParseError> {
+  let seat = line
+    .strip_prefix(lang.seat)
     .ok_or(ParseError::err(ParseErrorType::Start, "Position not found"))?;
-  let position = capture_position[0]
-    .replace(':', "")
+  let (position, after) = seat
+    .split_once(':')
+    .ok_or(ParseError::err(ParseErrorType::Start, "Position not found"))?;
+  let position = position
+    .trim()
     .parse::<u8>()
     .map_err(|e| ParseError::err(ParseErrorType::Start, e))?;
-  let capture_name = re::AFTER_COLON_P
-    .captures(line)
-    .ok_or(ParseError::err(ParseErrorType::Start, "Name not found"))?;
-  let name = String::from(capture_name[0].replace([':', '('], "").trim());
+  // the position indexes the seats of the hand
+  if !(1..=seats).contains(&(position as usize)) {
+    return Err(ParseError::err(
+      ParseErrorType::Start,
+      format!("no seat {}", position),
+    ));
+  }
+  // the stack is the last amount in brackets, the name may hold brackets and
+  // the client adds notes after the stack, "out of hand (moved from another
+  // table into small blind)"
   let capture_bank = re::MONEY_BRACED
-    .captures(line)
+    .find_iter(after)
+    .filter(|capture| after[..capture.start()].ends_with(' '))
+    .last()
     .ok_or(ParseError::err(ParseErrorType::Start, "Bank not found"))?;
-  let bank = capture_bank[0]
-    .replace(['$', '('], "")
-    .trim()
-    .parse::<f32>()
-    .map_err(|e| ParseError::err_msg(ParseErrorType::Start, e, &capture_bank[0]))?;
+  let name = String::from(after[..capture_bank.start()].trim());
+  let bank = parse_amount(capture_bank.as_str())
+    .map_err(|e| ParseError::err_msg(ParseErrorType::Start, e, capture_bank.as_str()))?;
   Ok(Player {
     name,
     position,
@@ -152,14 +240,21 @@ fn extract_seat(line: &str) -> Result<Player, ParseError> {
 }
 
 pub fn start(hand: &mut HandDetail, lines: &mut Lines) -> Result<(), ParseError> {
-  let first_line = lines.next().unwrap();
+  let first_line = lines
+    .next()
+    .ok_or(ParseError::err(ParseErrorType::Start, "empty hand"))?;
   hand.id = extract_id(first_line)?;
-  hand.date = extract_date(first_line)?;
+  hand.format = extract_format(first_line);
+  (hand.date, hand.local_date) = extract_dates(first_line)?;
 
   // NOTE: may be useless since we create blind object later
   (hand.small_limit, hand.big_limit) = extract_limits(first_line)?;
+  hand.currency = extract_currency(first_line)?;
+  hand.real_money = hand.currency != Currency::PlayMoney;
 
-  let second_line = lines.next().unwrap();
+  let second_line = lines
+    .next()
+    .ok_or(ParseError::err(ParseErrorType::Start, "missing table line"))?;
   hand.table_name = extract_table_name(second_line)?;
 
   // extract button position to latter shift and get actual position of the players
@@ -167,19 +262,33 @@ pub fn start(hand: &mut HandDetail, lines: &mut Lines) -> Result<(), ParseError>
   hand.table_size = extract_table_size(second_line)?;
 
   // not very optimized but very easy
+  // the seats come first, a player named "Seat 1" can't be mistaken for a seat
+  // once the blinds are posted
+  let lang = hand.language.lang();
+  let mut seats = true;
   for line in lines {
-    let mut split = line.split_whitespace();
-    if line.contains("posts small blind") {
-      hand.small_blind = extract_blind(hand, line)?;
-    } else if line.contains("posts big blind") {
-      hand.big_blind = extract_blind(hand, line)?;
-    } else if line.starts_with("Seat ") {
-      split.next(); // "Seat"
-      let player = extract_seat(line)?;
+    if line == lang.hole_cards {
+      return Ok(());
+    }
+    if seats && line.starts_with(lang.seat) {
+      let player = extract_seat(lang, line, hand.players.len())?;
       let position = player.position as usize - 1;
       hand.players[position] = Some(player);
-    } else if line == "*** HOLE CARDS ***" {
-      return Ok(());
+      continue;
+    }
+    seats = false;
+    match hand.split_player(line, lang.separator) {
+      Some((player, after)) if after.trim_start().starts_with(lang.small_blind) => {
+        hand.small_blind = extract_blind(player, after)?;
+      }
+      Some((player, after)) if after.trim_start().starts_with(lang.big_blind) => {
+        hand.big_blind = extract_blind(player, after)?;
+      }
+      _ => {
+        if let Some(event) = TableEvent::extract(hand, line, "start") {
+          hand.events.push(event);
+        }
+      }
     }
   }
   Ok(())
@@ -0,0 +1,512 @@
+use super::*;
+
+const HANDS: &str = include_str!("../../test/test_hands.txt");
+
+// Hands of the test file with the players renamed
+fn parse_renamed(names: &[(&str, &str)]) -> Vec<HandDetail> {
+  let mut content = HANDS.to_string();
+  for (name, new_name) in names {
+    content = content.replace(name, new_name);
+  }
+  parse_str(&content).unwrap()
+}
+
+// Actions without the names, to compare hands whose players were renamed
+fn shape(actions: &[Action]) -> Vec<String> {
+  actions
+    .iter()
+    .map(|action| match action {
+      Action::Call(p, amount, allin) => format!("{} call {} {}", p.position, amount, allin),
+      Action::Bet(p, amount, allin) => format!("{} bet {} {}", p.position, amount, allin),
+      Action::Raise(p, from, to, allin) => {
+        format!("{} raise {} {} {}", p.position, from, to, allin)
+      }
+      Action::Check(p) => format!("{} check", p.position),
+      Action::Fold(p) => format!("{} fold", p.position),
+      Action::Leave(p) => format!("{} leave", p.position),
+      Action::UncalledBet(p, amount) => format!("{} uncalled {}", p.position, amount),
+    })
+    .collect()
+}
+
+fn assert_same_hands(renamed: &[HandDetail]) {
+  let original = parse_str(HANDS).unwrap();
+  assert_eq!(original.len(), renamed.len());
+  for (original, renamed) in original.iter().zip(renamed) {
+    assert_eq!(shape(&original.preflop), shape(&renamed.preflop));
+    assert_eq!(shape(&original.flop), shape(&renamed.flop));
+    assert_eq!(shape(&original.turn), shape(&renamed.turn));
+    assert_eq!(shape(&original.river), shape(&renamed.river));
+    assert_eq!(original.players_card, renamed.players_card);
+    assert_eq!(original.end.pot, renamed.end.pot);
+    assert_eq!(original.end.winner.position, renamed.end.winner.position);
+    assert_eq!(original.small_blind.amount, renamed.small_blind.amount);
+    assert_eq!(
+      original.small_blind.player.position,
+      renamed.small_blind.player.position
+    );
+    assert_eq!(
+      original.big_blind.player.position,
+      renamed.big_blind.player.position
+    );
+  }
+}
+
+#[test]
+fn names_with_action_words() {
+  let hands = parse_renamed(&[
+    ("sidneivl", "checkmate"),
+    ("Savva08", "folds_alot"),
+    ("captelie52", "calls 2"),
+    ("alencarbrasil19", "bets"),
+    ("Cazunga", "Uncalled bet"),
+    ("carlitosbomba", "raises 4 to 5"),
+    ("haroldfried13", "leaves"),
+    ("gerdi2", "all-in"),
+  ]);
+  assert_same_hands(&hands);
+  assert_eq!(hands[0].players[0].as_ref().unwrap().name, "checkmate");
+  assert_eq!(hands[0].end.winner.name, "bets");
+}
+
+#[test]
+fn names_with_punctuation() {
+  let hands = parse_renamed(&[
+    ("sidneivl", "a: b"),
+    ("Savva08", "[Qh]"),
+    ("captelie52", "x (y)"),
+    ("alencarbrasil19", "z: folds [2c 7d]"),
+    ("Cazunga", "Seat 9: w ($5 in chips)"),
+    ("carlitosbomba", "\"quoted\" :"),
+    ("ArrAppA-Hi", "'t' #3"),
+    ("mrdee12", ":"),
+  ]);
+  assert_same_hands(&hands);
+  assert_eq!(hands[0].end.winner.name, "z: folds [2c 7d]");
+  assert_eq!(hands[1].end.winner.name, "\"quoted\" :");
+}
+
+#[test]
+fn seat_notes() {
+  let content = input::normalize(HANDS)
+    .replace(
+      "Seat 2: Savva08 ($1.96 in chips) \n",
+      "Seat 2: Savva08 ($1.96 in chips) is sitting out\n",
+    )
+    .replace(
+      "Seat 5: alencarbrasil19 ($1.59 in chips) \n",
+      "Seat 5: alencarbrasil19 ($1.59 in chips) out of hand (moved from another table into small blind)\n",
+    );
+  let hands = parse_str(&content).unwrap();
+  let seats: Vec<(&str, f32)> = hands[0]
+    .players
+    .iter()
+    .flatten()
+    .map(|player| (player.name.as_str(), player.bank))
+    .collect();
+  assert_eq!(seats[1], ("Savva08", 1.96));
+  assert_eq!(seats[4], ("alencarbrasil19", 1.59));
+  assert_same_hands(&hands);
+}
+
+#[test]
+fn names_with_hand_keywords() {
+  let hands = parse_renamed(&[
+    ("PokerZhyte", "Dealt to [me]"),
+    ("alencarbrasil19", "y returned to x"),
+    ("carlitosbomba", "v collected 5 from pot"),
+    ("ArrAppA-Hi", "u shows [Ah]"),
+  ]);
+  assert_same_hands(&hands);
+  assert_eq!(hands[0].big_blind.player.name, "Dealt to [me]");
+}
+
+#[test]
+fn names_prefix_of_others() {
+  // the longest seated name wins
+  let hands = parse_renamed(&[
+    ("sidneivl", "abc"),
+    ("Savva08", "abc: folds"),
+    ("carlitosbomba", "Arr"),
+    ("mrdee12", "ArrAppA-Hi: calls"),
+  ]);
+  assert_same_hands(&hands);
+  let fold = &hands[0].preflop[2];
+  assert_eq!(fold, &Action::Fold(hands[0].players[0].clone().unwrap()));
+  let fold = &hands[0].preflop[3];
+  assert_eq!(fold, &Action::Fold(hands[0].players[1].clone().unwrap()));
+}
+
+#[test]
+fn table_events() {
+  let content = input::normalize(HANDS)
+    .replace(
+      "PokerZhyte: posts big blind $0.02\n",
+      "PokerZhyte: posts big blind $0.02\nCazunga: sits out\nnewcomer joins the table at seat #7\n",
+    )
+    .replace(
+      "Cazunga: folds \n",
+      "Cazunga: folds \nsidneivl is disconnected\nsidneivl has timed out while disconnected\nSavva08 said, \"nh: folds\"\n",
+    )
+    .replace(
+      "alencarbrasil19: bets $0.18\n",
+      "alencarbrasil19: bets $0.18\nsidneivl is connected\ncaptelie52 cashed out the hand for $0.50 | Cash Out Fee $0.01\n",
+    );
+  let hands = parse_str(&content).unwrap();
+  let events: Vec<(&str, &str, &EventKind)> = hands[0]
+    .events
+    .iter()
+    .map(|event| (event.player.as_str(), event.moment, &event.kind))
+    .collect();
+  assert_eq!(
+    events,
+    vec![
+      ("Cazunga", "start", &EventKind::SitOut),
+      ("newcomer", "start", &EventKind::Join(7)),
+      ("sidneivl", "preflop", &EventKind::Disconnected),
+      ("sidneivl", "preflop", &EventKind::TimedOut),
+      (
+        "Savva08",
+        "preflop",
+        &EventKind::Chat("nh: folds".to_string())
+      ),
+      ("sidneivl", "turn", &EventKind::Connected),
+      ("captelie52", "turn", &EventKind::CashOut(0.5)),
+    ]
+  );
+  assert_eq!(
+    shape(&hands[0].preflop),
+    shape(&parse_str(HANDS).unwrap()[0].preflop)
+  );
+}
+
+#[test]
+fn run_twice() {
+  let content = input::normalize(HANDS).replace(
+    "*** RIVER *** [8c 7c Jc 9s] [8s]\n*** SHOW DOWN ***\n",
+    "*** FIRST RIVER *** [8c 7c Jc 9s] [8s]\n*** SECOND RIVER *** [8c 7c Jc 9s] [Ad]\n*** FIRST SHOW DOWN ***\n",
+  ).replace(
+    "carlitosbomba collected 20846 from pot\n",
+    "carlitosbomba collected 10423 from pot\n*** SECOND SHOW DOWN ***\nArrAppA-Hi collected 10423 from pot\n",
+  );
+  let hands = parse_str(&content).unwrap();
+  let hand = &hands[1];
+  assert_eq!(hand.boards.len(), 2);
+  assert!(hand.showdown);
+  assert_eq!(hand.river_card.as_deref(), Some("8s"));
+  let boards: Vec<Vec<&str>> = hand
+    .boards
+    .iter()
+    .map(|board| board.cards.iter().map(String::as_str).collect())
+    .collect();
+  assert_eq!(
+    boards,
+    vec![
+      vec!["8c", "7c", "Jc", "9s", "8s"],
+      vec!["8c", "7c", "Jc", "9s", "Ad"]
+    ]
+  );
+  assert_eq!(hand.board_share("carlitosbomba"), 0.5);
+  assert_eq!(hand.board_share("ArrAppA-Hi"), 0.5);
+  assert_eq!(hand.board_share("PokerZhyte"), 0.);
+  assert_eq!(hand.collected("ArrAppA-Hi"), 10423.);
+  assert_eq!(
+    hand.players_card[5],
+    Some(["Ac".to_string(), "6h".to_string()])
+  );
+
+  // a single board for the hands run once
+  assert_eq!(hands[0].boards.len(), 1);
+  assert_eq!(hands[0].boards[0].winners[0].0.name, "alencarbrasil19");
+}
+
+#[test]
+fn hero_dealt_in() {
+  let hands = parse_str(HANDS).unwrap();
+  for hand in &hands {
+    let hero = hand.hero.as_ref().unwrap();
+    assert_eq!(hero.name, "PokerZhyte");
+    assert!(hand.players_card[hero.position as usize - 1].is_some());
+  }
+}
+
+#[test]
+fn encoded_streams() {
+  let hands = parse_str(HANDS).unwrap();
+  let text = HANDS.trim_start_matches('\u{feff}');
+
+  // UTF-16 with a BOM, as written by older installs
+  let mut utf16 = vec![0xff, 0xfe];
+  for unit in text.encode_utf16() {
+    utf16.extend(unit.to_le_bytes());
+  }
+  let decoded = parse_reader(&utf16[..]).unwrap();
+  assert_eq!(decoded.len(), 2);
+  assert_eq!(decoded[0].id, hands[0].id);
+  assert_eq!(decoded[1].players, hands[1].players);
+
+  // Windows-1252, an accent in a name and euros
+  let text = text
+    .replace("Cazunga", "Zoé")
+    .replace('$', "€")
+    .replace(" USD)", " EUR)");
+  let (windows_1252, _, _) = encoding_rs::WINDOWS_1252.encode(&text);
+  assert!(std::str::from_utf8(&windows_1252).is_err());
+  let decoded = parse_bytes(&windows_1252).unwrap();
+  assert_eq!(decoded[0].currency, Currency::Eur);
+  assert_eq!(decoded[0].players[5].as_ref().unwrap().name, "Zoé");
+  assert_eq!(decoded[0].end, hands[0].end);
+}
+
+#[test]
+fn lenient_diagnostics() {
+  // the stack of a player of the first hand can't be read
+  let content = HANDS.replacen("($3.24 in chips)", "(lots in chips)", 1);
+  let report = parse_str_lenient(&content, "broken.txt");
+  assert_eq!(report.hands.len(), 1);
+  assert_eq!(report.diagnostics.len(), 1);
+  let diagnostic = &report.diagnostics[0];
+  assert_eq!(diagnostic.id, Some(249638850870));
+  assert!(diagnostic
+    .to_string()
+    .starts_with("broken.txt hand #0 (249638850870, byte 0): "));
+
+  // a seat out of the table is rejected, not a panic
+  for seat in ["Seat 0:", "Seat 10:"] {
+    let content = HANDS.replacen("Seat 1:", seat, 1);
+    let report = parse_str_lenient(&content, "seats.txt");
+    assert_eq!(report.hands.len(), 1);
+    assert_eq!(report.diagnostics[0].id, Some(249638850870));
+  }
+
+  // the quarantine reads as a history file
+  let path = std::env::temp_dir().join(format!("inspector-quarantine-{}.txt", std::process::id()));
+  let _ = std::fs::remove_file(&path);
+  write_quarantine(&path, &report.diagnostics).unwrap();
+  let quarantine = std::fs::read_to_string(&path).unwrap();
+  let again = parse_str_lenient(&quarantine, "quarantine.txt");
+  assert_eq!(again.diagnostics.len(), 1);
+  assert_eq!(again.diagnostics[0].id, Some(249638850870));
+  std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn zoom_headers() {
+  let hands = parse_str(HANDS).unwrap();
+  assert_eq!(hands[0].format, GameFormat::Cash);
+  assert_eq!(hands[1].format, GameFormat::Zoom);
+  // the table line names the pool
+  assert_eq!(hands[1].table_name, "NLHE 50/100 6 Max");
+
+  let zoom = &hands[1].content;
+  let real_money = zoom
+    .replace("(50/100)", "($0.02/$0.05)")
+    .replace("'NLHE 50/100 6 Max'", "'Aludra'");
+  let hand = parse_hand(&real_money).unwrap();
+  assert!(hand.is_zoom() && hand.real_money);
+  assert_eq!(hand.table_name, "Aludra");
+
+  // a Zoom pool named like a ring table and the other way round
+  let ring = zoom.replace("PokerStars Zoom Hand #", "PokerStars Hand #");
+  assert!(!parse_hand(&ring).unwrap().is_zoom());
+  let named = zoom.replace("'NLHE 50/100 6 Max'", "'Zoom Hand #1'");
+  let hand = parse_hand(&named).unwrap();
+  assert!(hand.is_zoom());
+  assert_eq!(hand.table_name, "Zoom Hand #1");
+}
+
+#[test]
+fn dates_and_time_zones() {
+  let hands = parse_str(HANDS).unwrap();
+  let with_date = |date: &str| {
+    hands[0]
+      .content
+      .replace("2024/03/26 22:02:04 CET [2024/03/26 17:02:04 ET]", date)
+  };
+  let dates = |date: &str| {
+    let hand = parse_hand(&with_date(date)).unwrap();
+    (
+      hand.date.to_rfc3339(),
+      hand.local_date.map(|date| date.to_rfc3339()),
+    )
+  };
+
+  assert_eq!(
+    dates("2024/03/26 22:02:04 CET [2024/03/26 17:02:04 ET]"),
+    (
+      "2024-03-26T21:02:04+00:00".to_string(),
+      Some("2024-03-26T22:02:04+01:00".to_string())
+    )
+  );
+  // ET follows the daylight saving time, -5 in winter and -4 in summer
+  assert_eq!(
+    dates("2024/01/15 12:00:00 ET").0,
+    "2024-01-15T17:00:00+00:00"
+  );
+  assert_eq!(
+    dates("2024/07/01 12:00:00 ET").0,
+    "2024-07-01T16:00:00+00:00"
+  );
+  assert_eq!(
+    dates("2024/03/10 03:30:00 ET").0,
+    "2024-03-10T07:30:00+00:00"
+  );
+  // the hour after 1:59 EDT is played again in EST, the first one is kept
+  assert_eq!(
+    dates("2024/11/03 01:30:00 ET").0,
+    "2024-11-03T05:30:00+00:00"
+  );
+  assert_eq!(
+    dates("2024/11/03 02:30:00 ET").0,
+    "2024-11-03T07:30:00+00:00"
+  );
+  assert_eq!(
+    dates("2024/07/01 18:00:00 CEST [2024/07/01 12:00:00 ET]").1,
+    Some("2024-07-01T18:00:00+02:00".to_string())
+  );
+
+  // an unknown local zone is skipped, the ET time remains
+  assert_eq!(
+    dates("2024/03/27 05:02:04 SGT [2024/03/26 17:02:04 ET]"),
+    ("2024-03-26T21:02:04+00:00".to_string(), None)
+  );
+  assert_eq!(
+    dates("2024/03/27 05:02:04 HKT [2024/03/26 17:02:04 ET]").0,
+    "2024-03-26T21:02:04+00:00"
+  );
+  assert!(parse_hand(&with_date("2024/03/27 05:02:04 SGT")).is_err());
+}
+
+#[test]
+fn currencies() {
+  let hands = parse_str(HANDS).unwrap();
+  assert_eq!(
+    (hands[0].currency, hands[0].real_money),
+    (Currency::Usd, true)
+  );
+  assert_eq!(
+    (hands[1].currency, hands[1].real_money),
+    (Currency::PlayMoney, false)
+  );
+
+  let currency = |limits: &str| {
+    let content = hands[0].content.replace("($0.01/$0.02 USD)", limits);
+    parse_hand(&content).map(|hand| (hand.currency, hand.real_money))
+  };
+  // the code after the limits
+  assert_eq!(
+    currency("(€0.01/€0.02 EUR)").unwrap(),
+    (Currency::Eur, true)
+  );
+  assert_eq!(currency("(£0.01/£0.02 GBP)").unwrap().0, Currency::Gbp);
+  assert_eq!(currency("($0.01/$0.02 CAD)").unwrap().0, Currency::Cad);
+  assert_eq!(currency("(₹1/₹2 INR)").unwrap().0, Currency::Inr);
+  assert!(currency("($0.01/$0.02 XYZ)").is_err());
+  // only the symbol, before or after the amount
+  assert_eq!(currency("($0.01/$0.02)").unwrap().0, Currency::Usd);
+  assert_eq!(currency("(€0.01/€0.02)").unwrap().0, Currency::Eur);
+  assert_eq!(currency("(0,01 €/0,02 €)").unwrap().0, Currency::Eur);
+  assert_eq!(currency("(£0.01/£0.02)").unwrap().0, Currency::Gbp);
+  assert_eq!(currency("(₹1/₹2)").unwrap().0, Currency::Inr);
+  assert_eq!(currency("(1/2)").unwrap(), (Currency::PlayMoney, false));
+}
+
+// The same hand written by the client in each language. The fixtures are
+// translations of test_hands.txt, not exports of a client, they only show the
+// words of lang.rs are read. Replace them by real histories once available.
+#[test]
+fn languages() {
+  let fixtures = [
+    (
+      include_str!("../../test/hands_fr.txt"),
+      Language::French,
+      Currency::Eur,
+    ),
+    (
+      include_str!("../../test/hands_de.txt"),
+      Language::German,
+      Currency::Eur,
+    ),
+    (
+      include_str!("../../test/hands_es.txt"),
+      Language::Spanish,
+      Currency::Eur,
+    ),
+    (
+      include_str!("../../test/hands_pt.txt"),
+      Language::Portuguese,
+      Currency::Usd,
+    ),
+    (
+      include_str!("../../test/hands_it.txt"),
+      Language::Italian,
+      Currency::Eur,
+    ),
+  ];
+  let hands: Vec<HandDetail> = fixtures
+    .iter()
+    .map(|(content, language, currency)| {
+      let mut hands = parse_str(content).unwrap();
+      assert_eq!(hands.len(), 1);
+      let hand = hands.remove(0);
+      assert_eq!((hand.language, hand.currency), (*language, *currency));
+      hand
+    })
+    .collect();
+
+  // "name : action" in French
+  let french = &hands[0];
+  assert_eq!(french.hero.as_ref().unwrap().name, "PokerZhyte");
+  assert_eq!(french.button_position, 2);
+  assert_eq!(
+    (french.small_blind.amount, french.big_blind.amount),
+    (0.01, 0.02)
+  );
+  assert_eq!(
+    shape(&french.preflop),
+    [
+      "5 raise 0.04 0.06 false",
+      "1 fold",
+      "2 call 0.06 false",
+      "3 fold",
+      "4 call 0.04 false"
+    ]
+  );
+  assert_eq!(
+    shape(&french.river),
+    ["4 check", "5 bet 0.51 true", "4 call 0.51 false"]
+  );
+  assert_eq!(
+    french.players_card[4],
+    Some(["9h".to_string(), "9d".to_string()])
+  );
+  assert_eq!(
+    (french.end.winner.name.as_str(), french.end.pot),
+    ("alencarbrasil19", 3.1)
+  );
+  assert_eq!(french.date.to_rfc3339(), "2024-04-02T19:15:30+00:00");
+  // "Cazunga: setzt aus" sits out in German, it isn't a bet
+  let events = |hand: &HandDetail| {
+    hand
+      .events
+      .iter()
+      .map(|event| (event.player.clone(), event.moment, event.kind.clone()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    events(french),
+    [("Cazunga".to_string(), "preflop", EventKind::SitOut)]
+  );
+
+  for hand in &hands[1..] {
+    assert_eq!(hand.players, french.players, "{}", hand.language);
+    assert_eq!(hand.players_card, french.players_card);
+    assert_eq!(shape(&hand.preflop), shape(&french.preflop));
+    assert_eq!(shape(&hand.flop), shape(&french.flop));
+    assert_eq!(shape(&hand.turn), shape(&french.turn));
+    assert_eq!(shape(&hand.river), shape(&french.river));
+    assert_eq!(hand.end, french.end);
+    assert_eq!(events(hand), events(french), "{}", hand.language);
+    assert_eq!(hand.date, french.date);
+  }
+}
@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Ranges of hole cards in the usual notation: "22+, A2s+, KTo+, 76s-54s",
+// a class of hands ("AKs", "AKo" or "AK" for both), a specific combo
+// ("AhKh"), and a weight after a colon ("AKs:0.5") for the hands only
+// played part of the time.
+
+const RANKS: [char; 13] = [
+  '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
+];
+const SUITS: [char; 4] = ['c', 'd', 'h', 's'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeError {
+  pub token: String, // part of the range that can't be read
+}
+
+impl fmt::Display for RangeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "can't read {:?} in the range", self.token)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Card {
+  pub rank: u8, // 0 for a deuce to 12 for an ace
+  pub suit: u8,
+}
+
+impl Card {
+  pub fn parse(card: &str) -> Option<Self> {
+    let mut chars = card.chars();
+    let rank = rank(chars.next()?)?;
+    let suit = chars.next()?;
+    let card = Card {
+      rank,
+      suit: SUITS.iter().position(|&s| s == suit)? as u8,
+    };
+    chars.next().is_none().then_some(card)
+  }
+}
+
+impl fmt::Display for Card {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "{}{}",
+      RANKS[self.rank as usize], SUITS[self.suit as usize]
+    )
+  }
+}
+
+/// Two hole cards, the highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Combo(pub Card, pub Card);
+
+impl Combo {
+  pub fn new(a: Card, b: Card) -> Self {
+    if a > b {
+      Combo(a, b)
+    } else {
+      Combo(b, a)
+    }
+  }
+
+  /// Combo of the cards of a history, ["Ah", "Kh"]
+  pub fn from_cards(cards: &[String; 2]) -> Option<Self> {
+    Some(Combo::new(Card::parse(&cards[0])?, Card::parse(&cards[1])?))
+  }
+
+  pub fn uses(&self, card: &Card) -> bool {
+    self.0 == *card || self.1 == *card
+  }
+
+  // row and column in the matrix, the suited hands above the diagonal
+  fn cell(&self) -> (usize, usize) {
+    let (high, low) = (12 - self.0.rank as usize, 12 - self.1.rank as usize);
+    if self.0.suit == self.1.suit {
+      (high, low)
+    } else {
+      (low, high)
+    }
+  }
+}
+
+impl fmt::Display for Combo {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}{}", self.0, self.1)
+  }
+}
+
+/// Weight of each combo in the range, the combos out of it aren't kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Range {
+  text: String,
+  combos: BTreeMap<Combo, f32>,
+}
+
+impl Range {
+  pub fn parse(text: &str) -> Result<Self, RangeError> {
+    let mut combos = BTreeMap::new();
+    for token in text
+      .split(',')
+      .map(str::trim)
+      .filter(|token| !token.is_empty())
+    {
+      let error = || RangeError {
+        token: token.to_string(),
+      };
+      let (hands, weight) = match token.split_once(':') {
+        Some((hands, weight)) => (
+          hands,
+          weight
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|weight| (0. ..=1.).contains(weight))
+            .ok_or_else(error)?,
+        ),
+        None => (token, 1.),
+      };
+      for combo in expand(hands.trim()).ok_or_else(error)? {
+        // the last weight given wins, "AK, AKs:0.5"
+        combos.insert(combo, weight);
+      }
+    }
+    combos.retain(|_, weight| *weight > 0.);
+    Ok(Range {
+      text: text.to_string(),
+      combos,
+    })
+  }
+
+  /// Weight of the combo, 0 when it's out of the range
+  pub fn weight(&self, combo: &Combo) -> f32 {
+    self.combos.get(combo).copied().unwrap_or(0.)
+  }
+
+  pub fn contains(&self, combo: &Combo) -> bool {
+    self.weight(combo) > 0.
+  }
+
+  /// Combos left once the known cards are removed, with their weight
+  pub fn combos(&self, dead: &[Card]) -> Vec<(Combo, f32)> {
+    self
+      .combos
+      .iter()
+      .filter(|(combo, _)| !dead.iter().any(|card| combo.uses(card)))
+      .map(|(combo, weight)| (*combo, *weight))
+      .collect()
+  }
+
+  /// Number of combos left, weighted
+  pub fn count(&self, dead: &[Card]) -> f32 {
+    self.combos(dead).iter().map(|(_, weight)| weight).sum()
+  }
+
+  /// The 13x13 grid, aces first, suited hands above the diagonal
+  pub fn matrix(&self, dead: &[Card]) -> RangeMatrix {
+    let mut cells: Vec<Vec<Cell>> = (0..13)
+      .map(|row| (0..13).map(|column| Cell::new(row, column)).collect())
+      .collect();
+    for combo in all_combos() {
+      let (row, column) = combo.cell();
+      let cell = &mut cells[row][column];
+      if !dead.iter().any(|card| combo.uses(card)) {
+        cell.available += 1;
+        cell.combos += self.weight(&combo);
+      }
+    }
+    for cell in cells.iter_mut().flatten() {
+      if cell.available > 0 {
+        cell.weight = cell.combos / cell.available as f32;
+      }
+    }
+    RangeMatrix {
+      combos: self.count(dead),
+      cells,
+    }
+  }
+}
+
+impl fmt::Display for Range {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.text)
+  }
+}
+
+impl TryFrom<String> for Range {
+  type Error = RangeError;
+
+  fn try_from(text: String) -> Result<Self, Self::Error> {
+    Range::parse(&text)
+  }
+}
+
+impl From<Range> for String {
+  fn from(range: Range) -> Self {
+    range.text
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cell {
+  pub label: String, // "AKs", "AKo" or "AA"
+  pub weight: f32,   // part of the combos left in the range
+  pub combos: f32,   // combos left in the range, weighted
+  available: u8,     // combos left by the known cards
+}
+
+impl Cell {
+  fn new(row: usize, column: usize) -> Self {
+    let (high, low) = (RANKS[12 - row.min(column)], RANKS[12 - row.max(column)]);
+    let label = match row.cmp(&column) {
+      std::cmp::Ordering::Equal => format!("{}{}", high, low),
+      std::cmp::Ordering::Less => format!("{}{}s", high, low),
+      std::cmp::Ordering::Greater => format!("{}{}o", high, low),
+    };
+    Cell {
+      label,
+      weight: 0.,
+      combos: 0.,
+      available: 0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RangeMatrix {
+  pub combos: f32,           // combos of the range left, weighted
+  pub cells: Vec<Vec<Cell>>, // rows from the aces to the deuces
+}
+
+impl RangeMatrix {
+  /// Part of the 1326 combos in the range
+  pub fn percent(&self) -> f32 {
+    self.combos / 1326. * 100.
+  }
+}
+
+// text grid, the weight of each cell in percent
+impl fmt::Display for RangeMatrix {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for row in &self.cells {
+      let line: Vec<String> = row
+        .iter()
+        .map(|cell| {
+          let text = match cell.weight {
+            weight if weight <= 0. => ".".to_string(),
+            weight if weight >= 1. => cell.label.clone(),
+            weight => format!("{:.0}%", weight * 100.),
+          };
+          format!("{:>4}", text)
+        })
+        .collect();
+      writeln!(f, "{}", line.join(" "))?;
+    }
+    write!(f, "{:.1} combos, {:.1}%", self.combos, self.percent())
+  }
+}
+
+fn rank(c: char) -> Option<u8> {
+  RANKS
+    .iter()
+    .position(|&rank| rank == c.to_ascii_uppercase())
+    .map(|rank| rank as u8)
+}
+
+fn all_combos() -> Vec<Combo> {
+  let cards: Vec<Card> = (0..13)
+    .flat_map(|rank| (0..4).map(move |suit| Card { rank, suit }))
+    .collect();
+  let mut combos = Vec::new();
+  for (index, a) in cards.iter().enumerate() {
+    for b in &cards[index + 1..] {
+      combos.push(Combo::new(*a, *b));
+    }
+  }
+  combos
+}
+
+// A class of hands, "AKs", "AKo", "AK" or "AA"
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Class {
+  high: u8,
+  low: u8,
+  suited: Option<bool>, // both when none
+}
+
+impl Class {
+  fn parse(class: &str) -> Option<Self> {
+    let mut chars = class.chars();
+    let (a, b) = (rank(chars.next()?)?, rank(chars.next()?)?);
+    let suited = match chars.next() {
+      Some('s') => Some(true),
+      Some('o') => Some(false),
+      None => None,
+      Some(_) => return None,
+    };
+    if chars.next().is_some() || (a == b && suited == Some(true)) {
+      return None;
+    }
+    Some(Class {
+      high: a.max(b),
+      low: a.min(b),
+      suited,
+    })
+  }
+
+  fn is_pair(&self) -> bool {
+    self.high == self.low
+  }
+
+  fn combos(&self) -> Vec<Combo> {
+    all_combos()
+      .into_iter()
+      .filter(|combo| {
+        combo.0.rank == self.high
+          && combo.1.rank == self.low
+          && self
+            .suited
+            .map_or(true, |suited| suited == (combo.0.suit == combo.1.suit))
+      })
+      .collect()
+  }
+}
+
+// combos of a part of the range, without its weight
+fn expand(hands: &str) -> Option<Vec<Combo>> {
+  // a specific combo, "AhKh"
+  let cards = (hands.get(..2), hands.get(2..));
+  if let (Some(a), Some(b)) = cards {
+    if let (Some(a), Some(b)) = (Card::parse(a), Card::parse(b)) {
+      return (a != b).then(|| vec![Combo::new(a, b)]);
+    }
+  }
+
+  let classes: Vec<Class> = if let Some(class) = hands.strip_suffix('+') {
+    // the pairs above, or the kickers up to the highest card
+    let class = Class::parse(class)?;
+    let top = if class.is_pair() { 12 } else { class.high - 1 };
+    (class.low..=top)
+      .map(|low| Class {
+        high: if class.is_pair() { low } else { class.high },
+        low,
+        ..class
+      })
+      .collect()
+  } else if let Some((first, last)) = hands.split_once('-') {
+    // "TT-77", "A9s-A6s" or the connectors "76s-54s"
+    let (first, last) = (Class::parse(first)?, Class::parse(last)?);
+    let (first, last) = if first.low >= last.low {
+      (first, last)
+    } else {
+      (last, first)
+    };
+    let gap = first.high - first.low;
+    let same_high = first.high == last.high && !first.is_pair();
+    if first.suited != last.suited || !(same_high || last.high - last.low == gap) {
+      return None;
+    }
+    (last.low..=first.low)
+      .map(|low| Class {
+        high: if same_high { first.high } else { low + gap },
+        low,
+        ..first
+      })
+      .collect()
+  } else {
+    vec![Class::parse(hands)?]
+  };
+  Some(classes.iter().flat_map(Class::combos).collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn labels(range: &Range) -> Vec<String> {
+    range
+      .matrix(&[])
+      .cells
+      .iter()
+      .flatten()
+      .filter(|cell| cell.weight > 0.)
+      .map(|cell| cell.label.clone())
+      .collect()
+  }
+
+  #[test]
+  fn notation() {
+    let range = Range::parse("QQ+, A2s+, KTo+, 76s-54s").unwrap();
+    assert_eq!(
+      labels(&range),
+      vec![
+        "AA", "AKs", "AQs", "AJs", "ATs", "A9s", "A8s", "A7s", "A6s", "A5s", "A4s", "A3s", "A2s",
+        "KK", "KQo", "QQ", "KJo", "KTo", "76s", "65s", "54s"
+      ]
+    );
+    // 3 pairs, 12 suited aces, 3 offsuit kings, 3 suited connectors
+    assert_eq!(range.count(&[]), 18. + 48. + 36. + 12.);
+
+    let range = Range::parse("TT-88, A9s-A7s, AK:0.5, AhKh").unwrap();
+    assert_eq!(labels(&range).len(), 3 + 3 + 2);
+    let combo = |a: &str, b: &str| Combo::new(Card::parse(a).unwrap(), Card::parse(b).unwrap());
+    assert_eq!(range.weight(&combo("Kh", "Ah")), 1.);
+    assert_eq!(range.weight(&combo("As", "Kh")), 0.5);
+    assert!(!range.contains(&combo("Qs", "Qh")));
+
+    for invalid in ["AKx", "AAs", "A1", "76s-54o", "AK:2", "T9s-72s"] {
+      assert!(Range::parse(invalid).is_err(), "{}", invalid);
+    }
+  }
+
+  #[test]
+  fn card_removal_and_matrix() {
+    let range = Range::parse("AA, AKs").unwrap();
+    let dead = [Card::parse("Ah").unwrap(), Card::parse("2c").unwrap()];
+    // 3 of the 6 aces pairs and 3 of the 4 suited AK are left
+    assert_eq!(range.count(&dead), 6.);
+
+    let matrix = Range::parse("AKs:0.5, 22").unwrap().matrix(&[]);
+    assert_eq!(matrix.cells[0][1].label, "AKs");
+    assert_eq!(matrix.cells[0][1].weight, 0.5);
+    assert_eq!(matrix.cells[1][0].label, "AKo");
+    assert_eq!(matrix.cells[12][12].label, "22");
+    assert_eq!(matrix.combos, 2. + 6.);
+    let text = matrix.to_string();
+    assert!(text.starts_with("   .  50%"));
+    assert!(text.ends_with("8.0 combos, 0.6%"));
+  }
+}
@@ -0,0 +1,339 @@
+use diesel::SqliteConnection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::db::{self, models, DBError};
+use crate::parse::HandDetail;
+use crate::replay;
+
+// The registry keeps the tables open in the client, fed by the hands of the
+// live import. The hands of a Zoom pool all carry the pool name while the hero
+// may play several tables of the pool at once, so each of those tables gets
+// its own entry, named after the pool and numbered.
+
+/// A player at an open table, with the stack left after the last hand
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeatedPlayer {
+  pub position: u8,
+  pub name: String,
+  pub stack: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+  pub key: String,        // name in the registry, the pool and a number for Zoom
+  pub table_name: String, // pool name for Zoom
+  pub table_size: u8,
+  pub button_position: u8,
+  pub zoom: bool,
+  pub real_money: bool,
+  pub last_hand: i64,
+  pub players: Vec<SeatedPlayer>,
+  updated: Instant, // when the last hand was imported
+}
+
+/// What a hand changed in the registry
+#[derive(Debug, PartialEq)]
+pub enum Update {
+  Opened,
+  SeatsChanged,
+  Unchanged, // same players, only the stacks or the button moved
+}
+
+/// Table sent to the overlay, each player with their stats
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSnapshot {
+  pub key: String,
+  pub table_name: String,
+  pub table_size: u8,
+  pub button_position: u8,
+  pub zoom: bool,
+  pub last_hand: i64,
+  pub seats: Vec<SeatSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeatSnapshot {
+  #[serde(flatten)]
+  pub player: SeatedPlayer,
+  pub stats: Option<models::Player>, // none until the player is imported
+}
+
+pub struct TableRegistry {
+  tables: HashMap<String, Table>,
+  timeout: Duration, // a table without hand for this long is closed
+}
+
+impl TableRegistry {
+  pub fn new(timeout: Duration) -> Self {
+    TableRegistry {
+      tables: HashMap::new(),
+      timeout,
+    }
+  }
+
+  /// Follow the table of the hand, return its key and what changed
+  pub fn update(&mut self, hand: &HandDetail, now: Instant) -> (String, Update) {
+    if hand.is_zoom() {
+      return self.update_zoom(hand, now);
+    }
+    let key = hand.table_name.clone();
+    let players = stacks_after(hand);
+    let update = match self.tables.get(&key) {
+      // a file imported again or out of order
+      Some(table) if table.last_hand > hand.id => return (key, Update::Unchanged),
+      Some(table) if same_seats(&table.players, &players) => Update::Unchanged,
+      Some(_) => Update::SeatsChanged,
+      None => Update::Opened,
+    };
+    self.insert(&key, hand, players, now);
+    (key, update)
+  }
+
+  // The players of a Zoom table change at almost every hand, the seats are
+  // compared as for the other tables so the HUD follows them.
+  // The hands of a table follow each other, so a hand goes to the table of the
+  // pool waiting the longest whose last hand started before it, hand ids
+  // growing with time, or opens another table of the pool.
+  fn update_zoom(&mut self, hand: &HandDetail, now: Instant) -> (String, Update) {
+    let pool: Vec<&Table> = self
+      .tables
+      .values()
+      .filter(|table| table.zoom && table.table_name == hand.table_name)
+      .filter(|table| !self.is_expired(table, now))
+      .collect();
+    if let Some(table) = pool.iter().find(|table| table.last_hand == hand.id) {
+      return (table.key.clone(), Update::Unchanged);
+    }
+    let players = stacks_after(hand);
+    let waiting = pool
+      .iter()
+      .filter(|table| table.last_hand < hand.id)
+      .min_by_key(|table| table.updated)
+      .map(|table| (table.key.clone(), same_seats(&table.players, &players)));
+
+    let (key, update) = match waiting {
+      Some((key, true)) => (key, Update::Unchanged),
+      Some((key, false)) => (key, Update::SeatsChanged),
+      None => {
+        let key = (1..)
+          .map(|number| format!("{} #{}", hand.table_name, number))
+          .find(|key| !self.tables.contains_key(key))
+          .unwrap();
+        (key, Update::Opened)
+      }
+    };
+    self.insert(&key, hand, players, now);
+    (key, update)
+  }
+
+  fn insert(&mut self, key: &str, hand: &HandDetail, players: Vec<SeatedPlayer>, now: Instant) {
+    self.tables.insert(
+      key.to_string(),
+      Table {
+        key: key.to_string(),
+        table_name: hand.table_name.clone(),
+        table_size: hand.table_size,
+        button_position: hand.button_position,
+        zoom: hand.is_zoom(),
+        real_money: hand.real_money,
+        last_hand: hand.id,
+        players,
+        updated: now,
+      },
+    );
+  }
+
+  /// Close the tables without hand for longer than the timeout, return their keys
+  pub fn expire(&mut self, now: Instant) -> Vec<String> {
+    let expired: Vec<String> = self
+      .tables
+      .values()
+      .filter(|table| self.is_expired(table, now))
+      .map(|table| table.key.clone())
+      .collect();
+    for key in &expired {
+      self.tables.remove(key);
+    }
+    expired
+  }
+
+  pub fn close_all(&mut self) -> Vec<String> {
+    self.tables.drain().map(|(key, _)| key).collect()
+  }
+
+  pub fn get(&self, key: &str) -> Option<&Table> {
+    self.tables.get(key)
+  }
+
+  /// Tables still open, the most recent hand first
+  pub fn tables(&self, now: Instant) -> Vec<&Table> {
+    let mut tables: Vec<&Table> = self
+      .tables
+      .values()
+      .filter(|table| !self.is_expired(table, now))
+      .collect();
+    tables.sort_by_key(|table| std::cmp::Reverse(table.last_hand));
+    tables
+  }
+
+  pub fn snapshot(
+    &self,
+    conn: &mut SqliteConnection,
+    now: Instant,
+  ) -> Result<Vec<TableSnapshot>, DBError> {
+    let mut snapshot = Vec::new();
+    for table in self.tables(now) {
+      let mut seats = Vec::new();
+      for player in &table.players {
+        seats.push(SeatSnapshot {
+          player: player.clone(),
+          stats: db::get_player(conn, &player.name, table.real_money)?,
+        });
+      }
+      snapshot.push(TableSnapshot {
+        key: table.key.clone(),
+        table_name: table.table_name.clone(),
+        table_size: table.table_size,
+        button_position: table.button_position,
+        zoom: table.zoom,
+        last_hand: table.last_hand,
+        seats,
+      });
+    }
+    Ok(snapshot)
+  }
+
+  fn is_expired(&self, table: &Table, now: Instant) -> bool {
+    now.saturating_duration_since(table.updated) > self.timeout
+  }
+}
+
+// stack of each player once the pot is shared
+fn stacks_after(hand: &HandDetail) -> Vec<SeatedPlayer> {
+  let last = replay::replay(hand).pop();
+  hand
+    .players
+    .iter()
+    .flatten()
+    .map(|player| {
+      let stack = last
+        .as_ref()
+        .and_then(|state| state.seats[player.position as usize - 1].as_ref())
+        .map_or(player.bank, |seat| seat.stack);
+      SeatedPlayer {
+        position: player.position,
+        name: player.name.clone(),
+        stack: replay::round(stack + hand.collected(&player.name)),
+      }
+    })
+    .collect()
+}
+
+fn same_seats(a: &[SeatedPlayer], b: &[SeatedPlayer]) -> bool {
+  a.len() == b.len()
+    && a
+      .iter()
+      .zip(b)
+      .all(|(a, b)| a.position == b.position && a.name == b.name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse;
+
+  const HANDS: &str = include_str!("../test/test_hands.txt");
+
+  #[test]
+  fn seats_and_stacks() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let mut registry = TableRegistry::new(Duration::from_secs(60));
+    let now = Instant::now();
+    let update = |registry: &mut TableRegistry, hand| registry.update(hand, now).1;
+    assert_eq!(update(&mut registry, &hands[0]), Update::Opened);
+    assert_eq!(update(&mut registry, &hands[0]), Update::Unchanged);
+    assert_eq!(update(&mut registry, &hands[1]), Update::Opened);
+
+    let table = registry.get("Ostara III").unwrap();
+    assert_eq!(table.button_position, 2);
+    let stacks: Vec<(&str, f32)> = table
+      .players
+      .iter()
+      .map(|player| (player.name.as_str(), player.stack))
+      .collect();
+    assert_eq!(
+      stacks,
+      vec![
+        ("sidneivl", 3.24),
+        ("Savva08", 1.96),
+        ("captelie52", 0.68),
+        ("PokerZhyte", 1.98),
+        ("alencarbrasil19", 1.63),
+        ("Cazunga", 2.),
+      ]
+    );
+    assert!(registry.get("NLHE 50/100 6 Max #1").unwrap().zoom);
+
+    // a player leaves the table
+    let mut next = parse::parse_str(HANDS).unwrap().remove(0);
+    next.id += 1;
+    next.players[0] = None;
+    assert_eq!(update(&mut registry, &next), Update::SeatsChanged);
+
+    let later = now + Duration::from_secs(61);
+    assert!(registry.tables(later).is_empty());
+    let mut expired = registry.expire(later);
+    expired.sort();
+    assert_eq!(expired, vec!["NLHE 50/100 6 Max #1", "Ostara III"]);
+  }
+
+  #[test]
+  fn zoom_tables() {
+    let hand = |id: i64, player: &str| {
+      let mut hand = parse::parse_str(HANDS).unwrap().remove(1);
+      hand.id = id;
+      hand.players[0].as_mut().unwrap().name = player.to_string();
+      hand
+    };
+    let mut registry = TableRegistry::new(Duration::from_secs(60));
+    let start = Instant::now();
+    let mut update =
+      |hand: &HandDetail, seconds: u64| registry.update(hand, start + Duration::from_secs(seconds));
+    let key = |number: u8| format!("NLHE 50/100 6 Max #{}", number);
+
+    assert_eq!(update(&hand(100, "a"), 0), (key(1), Update::Opened));
+    // started before the last hand of the table, it's played on another one
+    assert_eq!(update(&hand(99, "b"), 1), (key(2), Update::Opened));
+    // new opponents at the next hand of each table
+    assert_eq!(update(&hand(101, "c"), 2), (key(1), Update::SeatsChanged));
+    assert_eq!(update(&hand(102, "d"), 3), (key(2), Update::SeatsChanged));
+    assert_eq!(update(&hand(102, "d"), 4), (key(2), Update::Unchanged));
+    // the same opponents again
+    assert_eq!(update(&hand(103, "c"), 5), (key(1), Update::Unchanged));
+    assert_eq!(update(&hand(104, "e"), 6), (key(2), Update::SeatsChanged));
+
+    let now = start + Duration::from_secs(6);
+    let tables = registry.tables(now);
+    assert_eq!(tables.len(), 2);
+    assert!(tables
+      .iter()
+      .all(|table| table.zoom && table.table_name == "NLHE 50/100 6 Max"));
+    assert_eq!(registry.get(&key(1)).unwrap().players[0].name, "c");
+    assert_eq!(registry.get(&key(2)).unwrap().players[0].name, "e");
+
+    // the first table is left, the second one goes on
+    let later = start + Duration::from_secs(66);
+    assert_eq!(
+      registry.update(&hand(105, "f"), later),
+      (key(2), Update::SeatsChanged)
+    );
+    assert_eq!(registry.expire(later), vec![key(1)]);
+    // a table opened again takes the free number
+    assert_eq!(
+      registry.update(&hand(104, "g"), later),
+      (key(1), Update::Opened)
+    );
+  }
+}
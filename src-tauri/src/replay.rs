@@ -0,0 +1,447 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::parse::{Action, HandDetail};
+
+// The replay derives the state of the table from the actions of a hand.
+// Amounts follow the history: a call or a bet gives the chips added by the
+// player, a raise gives the total of the player on the street ("raises $2 to
+// $3"), and the uncalled bet is given back to the player.
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Street {
+  Preflop,
+  Flop,
+  Turn,
+  River,
+}
+
+impl fmt::Display for Street {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Street::Preflop => write!(f, "preflop"),
+      Street::Flop => write!(f, "flop"),
+      Street::Turn => write!(f, "turn"),
+      Street::River => write!(f, "river"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Seat {
+  pub name: String,
+  pub stack: f32,     // chips left in front of the player
+  pub committed: f32, // chips put in the pot on the current street
+  pub folded: bool,
+}
+
+/// State of the table after an action
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct State {
+  pub street: Street,
+  pub position: Option<u8>, // player who acted, none for the blinds
+  pub pot: f32,
+  pub current_bet: f32, // highest amount committed on the street
+  pub seats: [Option<Seat>; 9],
+  pub effective_stack: f32, // second largest stack of the players still in the hand
+  pub spr: f32,             // stack to pot ratio, effective stack / pot
+}
+
+impl State {
+  /// Amount the player has to add to call, bounded by the stack
+  pub fn to_call(&self, position: u8) -> f32 {
+    match &self.seats[position as usize - 1] {
+      Some(seat) => round((self.current_bet - seat.committed).max(0.).min(seat.stack)),
+      None => 0.,
+    }
+  }
+
+  /// Pot odds of the player, amount to call / pot after the call
+  pub fn pot_odds(&self, position: u8) -> f32 {
+    let to_call = self.to_call(position);
+    if to_call == 0. {
+      0.
+    } else {
+      to_call / (self.pot + to_call)
+    }
+  }
+
+  fn seat(&mut self, position: u8) -> Option<&mut Seat> {
+    self
+      .seats
+      .get_mut((position as usize).checked_sub(1)?)?
+      .as_mut()
+  }
+
+  // chips moved from the player to the pot, negative when given back
+  fn put(&mut self, position: u8, amount: f32) {
+    if let Some(seat) = self.seat(position) {
+      seat.stack = round(seat.stack - amount);
+      seat.committed = round(seat.committed + amount);
+      self.pot = round(self.pot + amount);
+    }
+  }
+
+  fn committed(&mut self, position: u8) -> f32 {
+    self.seat(position).map_or(0., |seat| seat.committed)
+  }
+
+  fn next_street(&mut self, street: Street) {
+    self.street = street;
+    self.position = None;
+    self.current_bet = 0.;
+    for seat in self.seats.iter_mut().flatten() {
+      seat.committed = 0.;
+    }
+  }
+
+  fn update_stacks(&mut self) {
+    let mut stacks: Vec<f32> = self
+      .seats
+      .iter()
+      .flatten()
+      .filter(|seat| !seat.folded)
+      .map(|seat| seat.stack)
+      .collect();
+    stacks.sort_by(|a, b| b.total_cmp(a));
+    // the largest stack can only lose what the second one can still put
+    self.effective_stack = stacks.get(1).map_or(0., |stack| round(*stack));
+    self.spr = if self.pot == 0. {
+      0.
+    } else {
+      self.effective_stack / self.pot
+    };
+  }
+
+  fn apply(&mut self, action: &Action) {
+    match action {
+      Action::Call(player, amount, _) | Action::Bet(player, amount, _) => {
+        self.position = Some(player.position);
+        self.put(player.position, *amount);
+        self.current_bet = self.current_bet.max(self.committed(player.position));
+      }
+      Action::Raise(player, _, to, _) => {
+        self.position = Some(player.position);
+        let added = to - self.committed(player.position);
+        self.put(player.position, added);
+        self.current_bet = *to;
+      }
+      Action::Check(player) => self.position = Some(player.position),
+      Action::Fold(player) | Action::Leave(player) => {
+        self.position = Some(player.position);
+        if let Some(seat) = self.seat(player.position) {
+          seat.folded = true;
+        }
+      }
+      Action::UncalledBet(player, amount) => {
+        self.position = Some(player.position);
+        self.put(player.position, -amount);
+        self.current_bet = self.current_bet.min(self.committed(player.position));
+      }
+    }
+  }
+}
+
+/// States of the hand: after the blinds, then after every action
+pub fn replay(hand: &HandDetail) -> Vec<State> {
+  let mut state = State {
+    street: Street::Preflop,
+    position: None,
+    pot: 0.,
+    current_bet: 0.,
+    seats: Default::default(),
+    effective_stack: 0.,
+    spr: 0.,
+  };
+  for (seat, player) in state.seats.iter_mut().zip(&hand.players) {
+    *seat = player.as_ref().map(|player| Seat {
+      name: player.name.clone(),
+      stack: player.bank,
+      committed: 0.,
+      folded: false,
+    });
+  }
+
+  // a missing blind has no player
+  for blind in [&hand.small_blind, &hand.big_blind] {
+    if blind.player.position != 0 {
+      state.put(blind.player.position, blind.amount);
+      state.current_bet = state.current_bet.max(blind.amount);
+    }
+  }
+  state.update_stacks();
+
+  let mut states = vec![state.clone()];
+  let streets = [
+    (Street::Preflop, &hand.preflop),
+    (Street::Flop, &hand.flop),
+    (Street::Turn, &hand.turn),
+    (Street::River, &hand.river),
+  ];
+  for (street, actions) in streets {
+    if street != Street::Preflop {
+      state.next_street(street);
+    }
+    for action in actions {
+      state.apply(action);
+      state.update_stacks();
+      states.push(state.clone());
+    }
+  }
+  states
+}
+
+/// Replay of a hand sent to the frontend to animate it
+#[derive(Debug, Clone, Serialize)]
+pub struct HandReplay {
+  pub id: i64,
+  pub table_name: String,
+  pub table_size: u8,
+  pub button_position: u8,
+  pub small_limit: f32,
+  pub big_limit: f32,
+  pub currency: String,
+  pub hero: Option<String>,
+  pub boards: Vec<ReplayBoard>, // two boards when the hand is run twice
+  pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayBoard {
+  pub cards: Vec<String>,
+  pub winners: Vec<(String, f32)>,
+}
+
+/// State of the table after an action, with what the player can see
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+  pub index: usize,
+  pub text: String,                    // "PokerZhyte: raises 400 to 500"
+  pub board: Vec<String>,              // cards dealt on the street, of the first board
+  pub cards: [Option<[String; 2]>; 9], // hole cards known, the hero's then the ones shown down
+  pub to_call: [f32; 9],               // amount each seat has to add to call
+  pub pot_odds: [f32; 9],              // amount to call / pot after the call
+  #[serde(flatten)]
+  pub state: State,
+}
+
+/// Steps of the replay: the blinds, every action, then the show down when the
+/// hand reaches it, which reveals the cards shown and every board
+pub fn hand_replay(hand: &HandDetail) -> HandReplay {
+  let mut hero_cards: [Option<[String; 2]>; 9] = Default::default();
+  if let Some(hero) = &hand.hero {
+    let position = hero.position as usize - 1;
+    hero_cards[position] = hand.players_card[position].clone();
+  }
+
+  let actions = hand
+    .preflop
+    .iter()
+    .chain(&hand.flop)
+    .chain(&hand.turn)
+    .chain(&hand.river);
+  let texts = std::iter::once(blinds_text(hand)).chain(actions.map(action_text));
+  let mut steps: Vec<Step> = replay(hand)
+    .into_iter()
+    .zip(texts)
+    .enumerate()
+    .map(|(index, (state, text))| Step {
+      index,
+      text,
+      board: street_board(hand, state.street),
+      cards: hero_cards.clone(),
+      to_call: seat_values(|position| state.to_call(position)),
+      pot_odds: seat_values(|position| state.pot_odds(position)),
+      state,
+    })
+    .collect();
+
+  if hand.showdown {
+    if let Some(last) = steps.last() {
+      let mut state = last.state.clone();
+      state.position = None;
+      steps.push(Step {
+        index: steps.len(),
+        text: "*** SHOW DOWN ***".to_string(),
+        board: street_board(hand, Street::River),
+        cards: hand.players_card.clone(),
+        to_call: [0.; 9],
+        pot_odds: [0.; 9],
+        state,
+      });
+    }
+  }
+
+  HandReplay {
+    id: hand.id,
+    table_name: hand.table_name.clone(),
+    table_size: hand.table_size,
+    button_position: hand.button_position,
+    small_limit: hand.small_limit,
+    big_limit: hand.big_limit,
+    currency: hand.currency.to_string(),
+    hero: hand.hero.as_ref().map(|hero| hero.name.clone()),
+    boards: hand
+      .boards
+      .iter()
+      .map(|board| ReplayBoard {
+        cards: board.cards.clone(),
+        winners: board
+          .winners
+          .iter()
+          .map(|(player, amount)| (player.name.clone(), *amount))
+          .collect(),
+      })
+      .collect(),
+    steps,
+  }
+}
+
+fn seat_values(value: impl Fn(u8) -> f32) -> [f32; 9] {
+  let mut values = [0.; 9];
+  for (position, slot) in (1..).zip(values.iter_mut()) {
+    *slot = value(position);
+  }
+  values
+}
+
+fn street_board(hand: &HandDetail, street: Street) -> Vec<String> {
+  let mut board = Vec::new();
+  if street == Street::Preflop {
+    return board;
+  }
+  if let Some(flop) = &hand.flop_card {
+    board.extend(flop.iter().cloned());
+  }
+  if street == Street::Turn || street == Street::River {
+    board.extend(hand.turn_card.iter().cloned());
+  }
+  if street == Street::River {
+    board.extend(hand.river_card.iter().cloned());
+  }
+  board
+}
+
+fn blinds_text(hand: &HandDetail) -> String {
+  [("small", &hand.small_blind), ("big", &hand.big_blind)]
+    .iter()
+    .filter(|(_, blind)| blind.player.position != 0)
+    .map(|(kind, blind)| {
+      format!(
+        "{}: posts {} blind {}",
+        blind.player.name, kind, blind.amount
+      )
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn action_text(action: &Action) -> String {
+  let all_in = |allin: &bool| if *allin { " and is all-in" } else { "" };
+  match action {
+    Action::Call(player, amount, allin) => {
+      format!("{}: calls {}{}", player.name, amount, all_in(allin))
+    }
+    Action::Bet(player, amount, allin) => {
+      format!("{}: bets {}{}", player.name, amount, all_in(allin))
+    }
+    Action::Raise(player, from, to, allin) => {
+      format!(
+        "{}: raises {} to {}{}",
+        player.name,
+        from,
+        to,
+        all_in(allin)
+      )
+    }
+    Action::Check(player) => format!("{}: checks", player.name),
+    Action::Fold(player) => format!("{}: folds", player.name),
+    Action::Leave(player) => format!("{} leaves the table", player.name),
+    Action::UncalledBet(player, amount) => {
+      format!("Uncalled bet ({}) returned to {}", amount, player.name)
+    }
+  }
+}
+
+pub(crate) fn round(amount: f32) -> f32 {
+  (amount * 100.).round() / 100.
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse;
+
+  const HANDS: &str = include_str!("../test/test_hands.txt");
+
+  #[test]
+  fn pot_matches_summary() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let pots: Vec<f32> = hands
+      .iter()
+      .map(|hand| replay(hand).last().unwrap().pot)
+      .collect();
+    // "Total pot $0.06", "Total pot 22059"
+    assert_eq!(pots, vec![0.06, 22059.]);
+  }
+
+  #[test]
+  fn stacks_and_bets() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let states = replay(&hands[1]);
+
+    // after the blinds
+    assert_eq!(states[0].pot, 150.);
+    assert_eq!(states[0].current_bet, 100.);
+    assert_eq!(states[0].to_call(4), 100.);
+    assert_eq!(states[0].to_call(2), 50.);
+
+    // PokerZhyte: raises 400 to 500
+    let raise = &states[6];
+    assert_eq!(raise.position, Some(3));
+    assert_eq!(raise.current_bet, 500.);
+    assert_eq!(raise.pot, 900.);
+    assert_eq!(raise.seats[2].as_ref().unwrap().stack, 9500.);
+    assert_eq!(raise.to_call(2), 400.);
+
+    // carlitosbomba: calls 5675 and is all-in
+    let all_in = states
+      .iter()
+      .find(|state| state.street == Street::Turn && state.position == Some(2) && state.pot > 18269.)
+      .unwrap();
+    assert_eq!(all_in.seats[1].as_ref().unwrap().stack, 0.);
+
+    // PokerZhyte: bets 1003, carlitosbomba has the second stack
+    let flop = states
+      .iter()
+      .find(|state| state.street == Street::Flop && state.position == Some(3))
+      .unwrap();
+    assert_eq!(flop.current_bet, 1003.);
+    assert_eq!(flop.pot, 2703.);
+    assert_eq!(flop.effective_stack, 8678.);
+    assert_eq!(flop.spr, 8678. / 2703.);
+  }
+
+  #[test]
+  fn replay_steps() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let hand_replay = hand_replay(&hands[1]);
+    let steps = &hand_replay.steps;
+    assert_eq!(steps[6].text, "PokerZhyte: raises 400 to 500");
+    assert_eq!(steps[6].board.len(), 0);
+    assert_eq!(steps[6].cards[2], hands[1].players_card[2]);
+    assert_eq!(steps[6].cards[1], None);
+    // carlitosbomba faces the raise
+    assert_eq!(steps[6].to_call[1], 400.);
+    assert_eq!(steps[6].pot_odds[1], 400. / 1300.);
+    assert_eq!(steps[6].to_call[2], 0.);
+
+    // the cards shown are revealed at the show down
+    let show_down = steps.last().unwrap();
+    assert_eq!(show_down.board, vec!["8c", "7c", "Jc", "9s", "8s"]);
+    assert_eq!(show_down.cards[1], hands[1].players_card[1]);
+    assert_eq!(hand_replay.boards[0].winners[0].0, "carlitosbomba");
+    assert!(serde_json::to_string(&hand_replay).is_ok());
+  }
+}
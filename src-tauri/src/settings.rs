@@ -0,0 +1,255 @@
+use dotenvy::dotenv;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::hero::Heroes;
+use crate::track::{WatchMode, WatchOptions};
+
+// Settings of the app, a JSON file in the config directory of the user
+// (~/.config/inspector/settings.json on Linux). INSPECTOR_SETTINGS gives
+// another file, for development.
+
+#[derive(Debug)]
+pub enum SettingsErrorType {
+  Read,
+  Write,
+  Invalid,
+}
+
+#[derive(Debug)]
+pub struct SettingsError {
+  t: SettingsErrorType,
+  msg: String,
+}
+
+impl SettingsError {
+  pub fn err(t: SettingsErrorType, e: impl std::string::ToString) -> Self {
+    SettingsError {
+      t,
+      msg: format!("Error : {}", e.to_string()),
+    }
+  }
+}
+
+impl fmt::Display for SettingsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{:?} {}", self.t, self.msg)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  pub history_dirs: Vec<PathBuf>, // folders where the client writes the histories
+  pub heroes: Vec<String>,        // accounts of the user
+  pub database: String,           // path of the SQLite database
+  pub auto_import: bool,          // import the history folders on startup
+  pub live_tracking: bool,        // watch the history folders while playing
+  pub time_zone: Option<String>,  // IANA name used to show the dates, the system one when none
+  pub watch_mode: WatchMode,      // poll the folders the system can't watch, SMB or NTFS mounts
+  pub poll_interval_ms: u64,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    dotenv().ok();
+    let database = env::var("DATABASE_URL")
+      .unwrap_or_else(|_| config_dir().join("db.sqlite").to_string_lossy().to_string());
+    Settings {
+      history_dirs: Vec::new(),
+      heroes: Heroes::from_env().aliases,
+      database,
+      auto_import: false,
+      live_tracking: false,
+      time_zone: None,
+      watch_mode: WatchMode::Auto,
+      poll_interval_ms: 2000,
+    }
+  }
+}
+
+impl Settings {
+  /// Read the settings, the default ones are written when the file doesn't exist
+  pub fn load(path: &Path) -> Result<Self, SettingsError> {
+    if !path.exists() {
+      let settings = Settings::default();
+      settings.save(path)?;
+      return Ok(settings);
+    }
+    let content =
+      std::fs::read_to_string(path).map_err(|e| SettingsError::err(SettingsErrorType::Read, e))?;
+    let settings: Settings =
+      serde_json::from_str(&content).map_err(|e| SettingsError::err(SettingsErrorType::Read, e))?;
+    settings.validate()?;
+    Ok(settings)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| SettingsError::err(SettingsErrorType::Write, e))?;
+    }
+    let content = serde_json::to_string_pretty(self)
+      .map_err(|e| SettingsError::err(SettingsErrorType::Write, e))?;
+    std::fs::write(path, content).map_err(|e| SettingsError::err(SettingsErrorType::Write, e))
+  }
+
+  pub fn validate(&self) -> Result<(), SettingsError> {
+    let invalid = |msg: String| Err(SettingsError::err(SettingsErrorType::Invalid, msg));
+    if self.database.trim().is_empty() {
+      return invalid("no database".to_string());
+    }
+    if let Some(hero) = self.heroes.iter().find(|hero| hero.trim().is_empty()) {
+      return invalid(format!("empty hero name {:?}", hero));
+    }
+    if let Some(time_zone) = &self.time_zone {
+      if time_zone.parse::<chrono_tz::Tz>().is_err() {
+        return invalid(format!("unknown time zone {}", time_zone));
+      }
+    }
+    if self.poll_interval_ms < 100 {
+      return invalid(format!("poll interval of {} ms", self.poll_interval_ms));
+    }
+    Ok(())
+  }
+
+  /// History folders found on the disk, a partition or a Wine prefix may be
+  /// missing for a while without losing its folders
+  pub fn available_dirs(&self) -> Vec<PathBuf> {
+    self
+      .history_dirs
+      .iter()
+      .filter(|dir| {
+        let found = dir.is_dir();
+        if !found {
+          log::warn!("{:?} is not a folder, skipped", dir);
+        }
+        found
+      })
+      .cloned()
+      .collect()
+  }
+
+  pub fn watch_options(&self) -> WatchOptions {
+    WatchOptions {
+      mode: self.watch_mode,
+      poll_interval: Duration::from_millis(self.poll_interval_ms),
+      ..WatchOptions::default()
+    }
+  }
+
+  pub fn heroes(&self) -> Heroes {
+    Heroes {
+      aliases: self.heroes.clone(),
+    }
+  }
+}
+
+fn config_dir() -> PathBuf {
+  dirs::config_dir()
+    .unwrap_or_else(|| PathBuf::from("."))
+    .join("inspector")
+}
+
+/// File of the settings
+pub fn path() -> PathBuf {
+  dotenv().ok();
+  match env::var("INSPECTOR_SETTINGS") {
+    Ok(path) => PathBuf::from(path),
+    Err(_) => config_dir().join("settings.json"),
+  }
+}
+
+/// Call `on_change` with the settings each time the file is written and
+/// still valid. The watch stops when the watcher is dropped.
+pub fn watch<F>(path: &Path, mut on_change: F) -> notify::Result<RecommendedWatcher>
+where
+  F: FnMut(Settings) + Send + 'static,
+{
+  let file = path.to_path_buf();
+  let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+    match res {
+      Ok(event) => {
+        // editors often write a new file and rename it over the old one
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+          || !event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == file.file_name())
+        {
+          return;
+        }
+        match Settings::load(&file) {
+          Ok(settings) => on_change(settings),
+          Err(e) => log::error!("Settings ignored : {}", e),
+        }
+      }
+      Err(error) => log::error!("Error: {error:?}"),
+    }
+  })?;
+  // the folder is watched, the file may be replaced
+  watcher.watch(
+    path.parent().unwrap_or(Path::new(".")),
+    RecursiveMode::NonRecursive,
+  )?;
+  Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn load_and_validate() {
+    let dir = env::temp_dir().join(format!("inspector-settings-{}", std::process::id()));
+    let path = dir.join("settings.json");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // written on first load
+    let settings = Settings::load(&path).unwrap();
+    assert!(path.exists());
+    assert_eq!(Settings::load(&path).unwrap(), settings);
+
+    // missing fields take the default value, the ones dropped are ignored
+    std::fs::write(
+      &path,
+      r#"{"database": "test.sqlite", "live_tracking": true, "watch_mode": "poll", "hud_profile": "tight"}"#,
+    )
+    .unwrap();
+    let settings = Settings::load(&path).unwrap();
+    assert_eq!(settings.database, "test.sqlite");
+    assert!(settings.live_tracking);
+    assert!(!settings.auto_import);
+    assert_eq!(settings.watch_mode, WatchMode::Poll);
+
+    let invalid = Settings {
+      time_zone: Some("Europe/Nowhere".to_string()),
+      ..settings.clone()
+    };
+    assert!(invalid.validate().is_err());
+    // a missing folder is kept but skipped
+    let missing = Settings {
+      history_dirs: vec![dir.join("missing"), dir.clone()],
+      ..settings.clone()
+    };
+    assert!(missing.validate().is_ok());
+    assert_eq!(missing.available_dirs(), vec![dir.clone()]);
+    let invalid = Settings {
+      poll_interval_ms: 10,
+      ..settings.clone()
+    };
+    assert!(invalid.validate().is_err());
+    let valid = Settings {
+      time_zone: Some("Europe/Brussels".to_string()),
+      history_dirs: vec![dir.clone()],
+      ..settings
+    };
+    assert!(valid.validate().is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,11 +1,37 @@
 use crate::db::models;
+use crate::hero::Heroes;
 use crate::parse;
+use crate::replay;
 
 // This use the HandDetai computed from the text
 // This means that these data can't be computed from the Hand directly from
 // the SQL database. However there's the textual content of the hand in the
 // SQL DB, which means you could recomputed HandDetail and all stats
-pub fn add(player: &mut models::Player, hands: &Vec<parse::HandDetail>) {
+// Stats are kept apart for real and play money, hands of the other kind are ignored
+pub fn add_hands(player: &mut models::Player, hands: &[&parse::HandDetail]) {
+  let name = player.name.clone();
+  add_played(player, hands.iter().map(|hand| (*hand, name.as_str())));
+}
+
+// Stats of the hero accounts merged in a single player. Each hand is counted
+// for the account that was dealt in, the hands of the other players are ignored.
+pub fn add_hero(player: &mut models::Player, hands: &[parse::HandDetail], heroes: &Heroes) {
+  add_played(
+    player,
+    hands.iter().filter_map(|hand| {
+      let hero = hand.hero.as_ref()?;
+      heroes
+        .is_hero(&hero.name)
+        .then_some((hand, hero.name.as_str()))
+    }),
+  );
+}
+
+// hands with the name the player had in each of them
+fn add_played<'a>(
+  player: &mut models::Player,
+  hands: impl Iterator<Item = (&'a parse::HandDetail, &'a str)>,
+) {
   let mut nb_vpip = player.vpip * player.nb_hand;
   let mut nb_pfr = player.pfr * player.nb_hand;
 
@@ -14,10 +40,14 @@ pub fn add(player: &mut models::Player, hands: &Vec<parse::HandDetail>) {
   let mut nb_cbet = player.cbet * player.nb_can_cbet;
   let mut nb_fold_cbet = player.fold_cbet * player.nb_can_fold_cbet;
   let mut nb_squeeze = player.squeeze * player.nb_can_squeeze;
+  let mut nb_wtsd = player.wtsd * player.nb_can_wtsd;
+  let mut nb_wsd = player.wsd * player.nb_can_wsd;
+  let mut won = player.winnings * player.nb_hand / 100.;
 
-  for hand in hands {
+  let real_money = player.real_money;
+  for (hand, name) in hands.filter(|(h, _)| h.real_money == real_money) {
     player.nb_hand += 1.;
-    let participation = PlayerParticipation::new(&hand, &player.name);
+    let participation = PlayerParticipation::new(hand, name);
     if participation.vpip {
       nb_vpip += 1.;
     }
@@ -58,6 +88,17 @@ pub fn add(player: &mut models::Player, hands: &Vec<parse::HandDetail>) {
       participation.can_squeeze,
       participation.squeeze,
     );
+    increase(
+      &mut nb_wtsd,
+      &mut player.nb_can_wtsd,
+      participation.can_wtsd,
+      participation.wtsd,
+    );
+    if participation.wtsd {
+      player.nb_can_wsd += 1.;
+      nb_wsd += participation.wsd;
+    }
+    won += participation.won;
   }
 
   player.vpip = divide(nb_vpip, player.nb_hand);
@@ -68,6 +109,12 @@ pub fn add(player: &mut models::Player, hands: &Vec<parse::HandDetail>) {
   player.cbet = divide(nb_cbet, player.nb_can_cbet);
   player.fold_cbet = divide(nb_fold_cbet, player.nb_can_fold_cbet);
   player.squeeze = divide(nb_squeeze, player.nb_can_squeeze);
+  player.wtsd = divide(nb_wtsd, player.nb_can_wtsd);
+  player.wsd = divide(nb_wsd, player.nb_can_wsd);
+  // the winnings can be negative, no -1 when there's no hand
+  if player.nb_hand > 0. {
+    player.winnings = won * 100. / player.nb_hand;
+  }
 }
 
 fn increase(nb_happen: &mut f32, nb_hand: &mut f32, condition: bool, happen: bool) {
@@ -90,7 +137,6 @@ fn divide(num: f32, den: f32) -> f32 {
 // All these stats can be split by position and moment
 // TODO : add fold to squeeze
 struct PlayerParticipation {
-  name: String,
   vpip: bool, // Volontary put in the pot. Without counting big-blind check
   pfr: bool,  // preflop raise. Count raise (3-bet and more)
 
@@ -107,7 +153,11 @@ struct PlayerParticipation {
   can_fold_cbet: bool,
   fold_cbet: bool, // fold to cbet flop
   can_squeeze: bool,
-  squeeze: bool, // raise after preflop raise and at least a player has call
+  squeeze: bool,  // raise after preflop raise and at least a player has call
+  can_wtsd: bool, // saw the flop
+  wtsd: bool,     // went to showdown
+  wsd: f32,       // part of the boards won at showdown, 0.5 for one of two boards
+  won: f32,       // net won in big blinds, with the pot of each board won
 }
 
 impl PlayerParticipation {
@@ -118,9 +168,9 @@ impl PlayerParticipation {
     let fold_cbet = fold_cbet_find(hand, name);
     let squeeze = squeeze_find(hand, name);
     let (call, bet, raise) = af_find(hand, name);
+    let wtsd = wtsd_find(hand, name);
 
     Self {
-      name: String::from(name),
       vpip: vpip_find(hand, name, Moment::Preflop),
       pfr: pfr_find(hand, name),
       call,
@@ -136,6 +186,13 @@ impl PlayerParticipation {
       fold_cbet: matches!(fold_cbet, Bool::True),
       can_squeeze: !matches!(squeeze, Bool::Impossible),
       squeeze: matches!(squeeze, Bool::True),
+      can_wtsd: !matches!(wtsd, Bool::Impossible),
+      wtsd: matches!(wtsd, Bool::True),
+      wsd: match wtsd {
+        Bool::True => hand.board_share(name),
+        _ => 0.,
+      },
+      won: won_find(hand, name),
     }
   }
 }
@@ -153,15 +210,13 @@ fn vpip_find(hand: &parse::HandDetail, name: &str, moment: Moment) -> bool {
     match action {
       parse::Action::Call(player, _, _)
       | parse::Action::Bet(player, _, _)
-      | parse::Action::Raise(player, _, _, _) => {
-        if name == player.name {
-          return true;
-        }
+      | parse::Action::Raise(player, _, _, _)
+        if name == player.name =>
+      {
+        return true;
       }
-      parse::Action::Check(player) => {
-        if is_big_blind && name == player.name {
-          next = true;
-        }
+      parse::Action::Check(player) if is_big_blind && name == player.name => {
+        next = true;
       }
       _ => {}
     };
@@ -202,20 +257,14 @@ fn af_find(hand: &parse::HandDetail, name: &str) -> (f32, f32, f32) {
     .chain(hand.river.iter())
   {
     match action {
-      parse::Action::Call(player, _, _) => {
-        if name == player.name {
-          call += 1.;
-        }
+      parse::Action::Call(player, _, _) if name == player.name => {
+        call += 1.;
       }
-      parse::Action::Bet(player, _, _) => {
-        if name == player.name {
-          bet += 1.;
-        }
+      parse::Action::Bet(player, _, _) if name == player.name => {
+        bet += 1.;
       }
-      parse::Action::Raise(player, _, _, _) => {
-        if name == player.name {
-          raise += 1.;
-        }
+      parse::Action::Raise(player, _, _, _) if name == player.name => {
+        raise += 1.;
       }
       _ => {}
     }
@@ -223,6 +272,67 @@ fn af_find(hand: &parse::HandDetail, name: &str) -> (f32, f32, f32) {
   (call, bet, raise)
 }
 
+// went to showdown, only counted when the player saw the flop
+fn wtsd_find(hand: &parse::HandDetail, name: &str) -> Bool {
+  let folded = |actions: &Vec<parse::Action>| {
+    actions.iter().any(|action| match action {
+      parse::Action::Fold(player) | parse::Action::Leave(player) => player.name == name,
+      _ => false,
+    })
+  };
+  let played = hand.preflop.iter().any(|action| match action {
+    parse::Action::Call(player, _, _)
+    | parse::Action::Bet(player, _, _)
+    | parse::Action::Raise(player, _, _, _)
+    | parse::Action::Check(player) => player.name == name,
+    _ => false,
+  });
+  if hand.flop_card.is_none() || !played || folded(&hand.preflop) {
+    return Bool::Impossible;
+  }
+  if hand.showdown && !folded(&hand.flop) && !folded(&hand.turn) && !folded(&hand.river) {
+    Bool::True
+  } else {
+    Bool::False
+  }
+}
+
+// net won in big blinds. Each board of a hand run twice has its own pot, the
+// player gets back what was collected on the boards won.
+fn won_find(hand: &parse::HandDetail, name: &str) -> f32 {
+  let bank = match hand
+    .players
+    .iter()
+    .flatten()
+    .find(|player| player.name == name)
+  {
+    Some(player) => player.bank,
+    None => return 0.,
+  };
+  let stack = replay::replay(hand)
+    .pop()
+    .and_then(|state| {
+      state
+        .seats
+        .into_iter()
+        .flatten()
+        .find(|seat| seat.name == name)
+    })
+    .map_or(bank, |seat| seat.stack);
+  let collected: f32 = hand
+    .boards
+    .iter()
+    .flat_map(|board| &board.winners)
+    .filter(|(player, _)| player.name == name)
+    .map(|(_, amount)| amount)
+    .sum();
+  if hand.big_limit == 0. {
+    0.
+  } else {
+    replay::round(stack + collected - bank) / hand.big_limit
+  }
+}
+
 fn pre_3bet_find(hand: &parse::HandDetail, name: &str) -> Bool {
   let mut raise_before = 0;
   for action in &hand.preflop {
@@ -238,10 +348,10 @@ fn pre_3bet_find(hand: &parse::HandDetail, name: &str) -> Bool {
           raise_before += 1
         }
       }
-      parse::Action::Call(player, _, _) | parse::Action::Fold(player) => {
-        if player.name == name && raise_before == 1 {
-          return Bool::False;
-        }
+      parse::Action::Call(player, _, _) | parse::Action::Fold(player)
+        if player.name == name && raise_before == 1 =>
+      {
+        return Bool::False;
       }
       _ => {}
     }
@@ -253,10 +363,8 @@ fn fold_pre_3bet_find(hand: &parse::HandDetail, name: &str) -> Bool {
   let mut raised = false; // opponent 3 bet
   for action in &hand.preflop {
     match action {
-      parse::Action::Bet(player, _, _) => {
-        if player.name != name {
-          return Bool::Impossible;
-        }
+      parse::Action::Bet(player, _, _) if player.name != name => {
+        return Bool::Impossible;
       }
       // At this point we know that 'name' has opened
       // If the player to play is 'name', 'raised' must be true
@@ -270,19 +378,15 @@ fn fold_pre_3bet_find(hand: &parse::HandDetail, name: &str) -> Bool {
           return Bool::Impossible;
         }
       }
-      parse::Action::Fold(player) => {
-        if name == player.name {
-          if raised {
-            return Bool::True;
-          } else {
-            return Bool::Impossible;
-          }
+      parse::Action::Fold(player) if name == player.name => {
+        if raised {
+          return Bool::True;
+        } else {
+          return Bool::Impossible;
         }
       }
-      parse::Action::Call(player, _, _) => {
-        if player.name == name && raised {
-          return Bool::False;
-        }
+      parse::Action::Call(player, _, _) if player.name == name && raised => {
+        return Bool::False;
       }
       _ => {}
     }
@@ -308,10 +412,8 @@ fn cbet_find(hand: &parse::HandDetail, name: &str) -> Bool {
 
   for action in &hand.flop {
     match action {
-      parse::Action::Check(player) => {
-        if name == player.name {
-          return Bool::False;
-        }
+      parse::Action::Check(player) if name == player.name => {
+        return Bool::False;
       }
       parse::Action::Bet(player, _, _) => {
         if name != player.name {
@@ -347,24 +449,20 @@ fn fold_cbet_find(hand: &parse::HandDetail, name: &str) -> Bool {
   // NOTE: in this case we only consider the case where opener can bet and nobody has bet before
   for action in &hand.flop {
     match action {
-      parse::Action::Bet(player, _, _) => {
-        if player.name != adversary {
-          return Bool::Impossible;
-        }
+      parse::Action::Bet(player, _, _) if player.name != adversary => {
+        return Bool::Impossible;
       }
 
       // if any other player raise, this doesn't count anymore
       // If we reach here, the opener has cbet already
-      parse::Action::Raise(player, _, _, _) | parse::Action::Call(player, _, _) => {
-        if player.name == name {
-          return Bool::False;
-        }
+      parse::Action::Raise(player, _, _, _) | parse::Action::Call(player, _, _)
+        if player.name == name =>
+      {
+        return Bool::False;
       }
 
-      parse::Action::Fold(player) => {
-        if player.name == name {
-          return Bool::True;
-        }
+      parse::Action::Fold(player) if player.name == name => {
+        return Bool::True;
       }
       _ => {}
     }
@@ -402,13 +500,11 @@ fn squeeze_find(hand: &parse::HandDetail, name: &str) -> Bool {
           caller = true;
         }
       }
-      parse::Action::Check(player) | parse::Action::Fold(player) => {
-        if player.name == name && open {
-          if caller {
-            return Bool::False;
-          } else {
-            return Bool::Impossible;
-          }
+      parse::Action::Check(player) | parse::Action::Fold(player) if player.name == name && open => {
+        if caller {
+          return Bool::False;
+        } else {
+          return Bool::Impossible;
         }
       }
       _ => {}
@@ -429,3 +525,62 @@ enum Moment {
   Turn,
   River,
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HANDS: &str = include_str!("../test/test_hands.txt");
+
+  // the players of a Zoom pool change every hand, the stats only follow the names
+  #[test]
+  fn zoom_hands() {
+    let hands = parse::parse_str(HANDS).unwrap();
+    let zoom = &hands[1];
+    assert!(zoom.is_zoom());
+
+    let mut hero = models::Player::new("PokerZhyte");
+    add_hands(&mut hero, &[zoom, zoom]);
+    assert_eq!(hero.nb_hand, 2.);
+    assert_eq!((hero.vpip, hero.pfr, hero.cbet), (1., 1., 1.));
+    assert_eq!(hero.wtsd, 0.);
+
+    let mut winner = models::Player::new("carlitosbomba");
+    add_hands(&mut winner, &[zoom]);
+    assert_eq!((winner.vpip, winner.pfr), (1., 0.));
+    assert_eq!((winner.wtsd, winner.wsd), (1., 1.));
+  }
+
+  // the pot of a hand run twice is shared between the boards
+  #[test]
+  fn run_twice_winnings() {
+    let content = parse::input::normalize(HANDS)
+      .replace(
+        "*** RIVER *** [8c 7c Jc 9s] [8s]\n*** SHOW DOWN ***\n",
+        "*** FIRST RIVER *** [8c 7c Jc 9s] [8s]\n*** SECOND RIVER *** [8c 7c Jc 9s] [Ad]\n*** FIRST SHOW DOWN ***\n",
+      )
+      .replace(
+        "carlitosbomba collected 20846 from pot\n",
+        "carlitosbomba collected 10423 from pot\n*** SECOND SHOW DOWN ***\nArrAppA-Hi collected 10423 from pot\n",
+      );
+    let single = parse::parse_str(HANDS).unwrap();
+    let twice = parse::parse_str(&content).unwrap();
+    let winnings = |hand: &parse::HandDetail, name: &str| {
+      let mut player = models::Player::new(name);
+      add_hands(&mut player, &[hand]);
+      player.winnings / 100.
+    };
+
+    // all-in for 9178 chips on the turn
+    assert_eq!(winnings(&single[1], "carlitosbomba"), 116.68);
+    assert_eq!(winnings(&single[1], "ArrAppA-Hi"), -91.78);
+    assert_eq!(winnings(&twice[1], "carlitosbomba"), 12.45);
+    assert_eq!(winnings(&twice[1], "ArrAppA-Hi"), 12.45);
+    assert_eq!(winnings(&twice[1], "PokerZhyte"), -35.03);
+
+    let mut player = models::Player::new("carlitosbomba");
+    add_hands(&mut player, &[&single[1], &twice[1]]);
+    assert_eq!(player.nb_hand, 2.);
+    assert_eq!((player.wsd, player.nb_can_wsd), (0.75, 2.));
+  }
+}
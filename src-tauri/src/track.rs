@@ -1,28 +1,549 @@
-use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc::{Receiver, Sender};
-
-pub fn watch(
-  path: &Path,
-  sender: Sender<Result<notify::Event, notify::Error>>,
-  receiver: Receiver<Result<notify::Event, notify::Error>>,
-) -> notify::Result<()> {
-  // Automatically select the best implementation for your platform.
-  // You can also access each implementation directly e.g. INotifyWatcher.
-  let mut watcher = RecommendedWatcher::new(sender, Config::default())?;
-
-  // Add a path to be watched. All files and directories at that path and
-  // below will be monitored for changes.
-  watcher.watch(path, RecursiveMode::Recursive)?;
-
-  std::thread::spawn(|| {
-    for res in receiver {
-      match res {
-        Ok(event) => log::info!("Change: {event:?}"),
+use diesel::SqliteConnection;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::db::{self, models};
+use crate::import;
+use crate::parse::{self, HandDetail};
+use crate::registry::{SeatedPlayer, TableRegistry, Update};
+
+/// How the history folders are watched
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchMode {
+  Native, // inotify and the like, told by the system of each change
+  Poll,   // the folders are scanned at each interval
+  Auto,   // poll the network and Windows mounts, where the system tells nothing
+}
+
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+  pub mode: WatchMode,
+  pub poll_interval: Duration,
+  pub debounce: Duration, // quiet time before a file written is imported
+}
+
+impl Default for WatchOptions {
+  fn default() -> Self {
+    WatchOptions {
+      mode: WatchMode::Auto,
+      poll_interval: Duration::from_secs(2),
+      debounce: Duration::from_millis(500),
+    }
+  }
+}
+
+/// A history file that changed under the watched folders
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+  Written(PathBuf), // created, modified or renamed to
+  Removed(PathBuf), // deleted or renamed from
+}
+
+/// Watch the folders for the history files written by the client while
+/// playing. The watch stops when the tracker is dropped, once the file being
+/// imported is done.
+pub struct Tracker {
+  watchers: Vec<Box<dyn Watcher + Send>>,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl Tracker {
+  /// Call `on_change` with each history file changed under the paths. A file
+  /// is only given once it stayed untouched for the debounce time, the client
+  /// writes a hand in several bursts.
+  pub fn start<F>(paths: &[PathBuf], options: &WatchOptions, on_change: F) -> notify::Result<Self>
+  where
+    F: FnMut(Change) + Send + 'static,
+  {
+    let (sender, receiver) = channel::<Change>();
+    let mut watchers: Vec<Box<dyn Watcher + Send>> = Vec::new();
+    for path in paths {
+      let sender = sender.clone();
+      let handler = move |res: notify::Result<notify::Event>| match res {
+        Ok(event) => {
+          for change in changes(&event) {
+            // the receiver only stops once the watchers are dropped
+            let _ = sender.send(change);
+          }
+        }
         Err(error) => log::error!("Error: {error:?}"),
+      };
+      let poll = match options.mode {
+        WatchMode::Native => false,
+        WatchMode::Poll => true,
+        WatchMode::Auto => needs_polling(path),
+      };
+      let mut watcher: Box<dyn Watcher + Send> = if poll {
+        let config = Config::default().with_poll_interval(options.poll_interval);
+        Box::new(PollWatcher::new(handler, config)?)
+      } else {
+        Box::new(RecommendedWatcher::new(handler, Config::default())?)
+      };
+      log::info!("Watching {:?}, polling {}", path, poll);
+      watcher.watch(path, RecursiveMode::Recursive)?;
+      watchers.push(watcher);
+    }
+
+    let debounce = options.debounce;
+    let handle = std::thread::spawn(move || debounced(receiver, debounce, on_change));
+    Ok(Tracker {
+      watchers,
+      handle: Some(handle),
+    })
+  }
+}
+
+impl Drop for Tracker {
+  fn drop(&mut self) {
+    // the senders go with the watchers, which stops the thread
+    self.watchers.clear();
+    if let Some(handle) = self.handle.take() {
+      let _ = handle.join();
+    }
+  }
+}
+
+// history files of the event, the renames give both paths
+fn changes(event: &notify::Event) -> Vec<Change> {
+  let history = event
+    .paths
+    .iter()
+    .filter(|path| import::is_history_file(path))
+    .cloned();
+  match event.kind {
+    // the old name then the new one, each kept when it's a history file, a
+    // file written under a temporary name and renamed is only written
+    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+      let renamed = |index: usize| {
+        event
+          .paths
+          .get(index)
+          .filter(|path| import::is_history_file(path))
+          .cloned()
+      };
+      renamed(0)
+        .map(Change::Removed)
+        .into_iter()
+        .chain(renamed(1).map(Change::Written))
+        .collect()
+    }
+    EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+      history.map(Change::Removed).collect()
+    }
+    EventKind::Create(_) | EventKind::Modify(_) => history.map(Change::Written).collect(),
+    _ => Vec::new(),
+  }
+}
+
+// Give the changes once the files are quiet. A file written then removed
+// before is only given as removed, the rename of a file being written too.
+fn debounced<F>(receiver: Receiver<Change>, debounce: Duration, mut on_change: F)
+where
+  F: FnMut(Change),
+{
+  let mut pending: Vec<(PathBuf, Instant)> = Vec::new();
+  loop {
+    let wait = pending
+      .iter()
+      .map(|(_, last)| debounce.saturating_sub(last.elapsed()))
+      .min()
+      .unwrap_or(debounce);
+    match receiver.recv_timeout(wait) {
+      Ok(Change::Written(path)) => {
+        pending.retain(|(pending, _)| *pending != path);
+        pending.push((path, Instant::now()));
+      }
+      Ok(Change::Removed(path)) => {
+        pending.retain(|(pending, _)| *pending != path);
+        on_change(Change::Removed(path));
+      }
+      Err(RecvTimeoutError::Timeout) => (),
+      Err(RecvTimeoutError::Disconnected) => return,
+    }
+
+    let (quiet, waiting): (Vec<_>, Vec<_>) = pending
+      .drain(..)
+      .partition(|(_, last)| last.elapsed() >= debounce);
+    pending = waiting;
+    for (path, _) in quiet {
+      // removed without event, as the polling can miss a quick rename
+      if path.exists() {
+        on_change(Change::Written(path));
+      } else {
+        on_change(Change::Removed(path));
+      }
+    }
+  }
+}
+
+// Network and Windows file systems, inotify doesn't see the files written
+// by other machines or by Windows itself
+fn needs_polling(path: &Path) -> bool {
+  const REMOTE: [&str; 9] = [
+    "cifs", "smb3", "smbfs", "nfs", "nfs4", "ntfs", "ntfs3", "fuseblk", "9p",
+  ];
+  let mounts = match std::fs::read_to_string("/proc/mounts") {
+    Ok(mounts) => mounts,
+    Err(_) => return false,
+  };
+  let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+  // the file system of the longest mount point holding the path
+  mounts
+    .lines()
+    .filter_map(|line| {
+      let mut fields = line.split_whitespace();
+      let _device = fields.next()?;
+      let mount_point = fields.next()?.replace("\\040", " ");
+      let fs_type = fields.next()?;
+      path
+        .starts_with(&mount_point)
+        .then(|| (mount_point.len(), fs_type.to_string()))
+    })
+    .max_by_key(|(length, _)| *length)
+    .map_or(false, |(_, fs_type)| {
+      REMOTE.contains(&fs_type.as_str()) || fs_type.starts_with("fuse.")
+    })
+}
+
+/// Events sent to the frontend while tracking, each kind under its own name.
+/// The table events name the table by its key in the registry, the pool and
+/// a number for the tables of a Zoom pool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+  HandImported {
+    id: i64,
+    table_name: String,
+    hero: Option<String>,
+  },
+  TableOpened {
+    table_name: String,
+  },
+  TableClosed {
+    table_name: String,
+  },
+  SeatsChanged {
+    table_name: String,
+    players: Vec<SeatedPlayer>,
+  },
+  PlayerUpdated {
+    player: models::Player,
+  },
+  ImportError {
+    file: String,
+    message: String,
+  },
+}
+
+impl LiveEvent {
+  pub fn name(&self) -> &'static str {
+    match self {
+      LiveEvent::HandImported { .. } => "hand-imported",
+      LiveEvent::TableOpened { .. } => "table-opened",
+      LiveEvent::TableClosed { .. } => "table-closed",
+      LiveEvent::SeatsChanged { .. } => "seats-changed",
+      LiveEvent::PlayerUpdated { .. } => "player-updated",
+      LiveEvent::ImportError { .. } => "import-error",
+    }
+  }
+
+  // events with the same key replace each other while waiting to be sent,
+  // only the last state of a table or a player matters
+  fn key(&self) -> Option<String> {
+    match self {
+      LiveEvent::SeatsChanged { table_name, .. } => Some(format!("seats {}", table_name)),
+      LiveEvent::PlayerUpdated { player } => {
+        Some(format!("player {} {}", player.name, player.real_money))
+      }
+      _ => None,
+    }
+  }
+}
+
+/// Where the live events go, the webview in the app
+pub trait EventSink: Send + 'static {
+  fn emit(&self, event: &LiveEvent);
+}
+
+/// Send the events to the sink at most once per interval. The events waiting
+/// are coalesced, so a fast Zoom session sends a player once per interval
+/// however many hands they play. Dropping the throttle sends what is left.
+pub struct Throttle {
+  sender: Option<Sender<LiveEvent>>,
+  handle: Option<JoinHandle<()>>,
+}
+
+impl Throttle {
+  pub fn new<S: EventSink>(sink: S, interval: Duration) -> Self {
+    let (sender, receiver) = channel::<LiveEvent>();
+    let handle = std::thread::spawn(move || {
+      let mut pending: Vec<LiveEvent> = Vec::new();
+      let mut last: Option<Instant> = None;
+      loop {
+        let wait = match last {
+          Some(last) if !pending.is_empty() => interval.saturating_sub(last.elapsed()),
+          _ => interval,
+        };
+        match receiver.recv_timeout(wait) {
+          Ok(event) => coalesce(&mut pending, event),
+          Err(RecvTimeoutError::Timeout) => (),
+          Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if !pending.is_empty() && last.map_or(true, |last| last.elapsed() >= interval) {
+          pending.drain(..).for_each(|event| sink.emit(&event));
+          last = Some(Instant::now());
+        }
       }
+      pending.iter().for_each(|event| sink.emit(event));
+    });
+    Throttle {
+      sender: Some(sender),
+      handle: Some(handle),
+    }
+  }
+}
+
+fn coalesce(pending: &mut Vec<LiveEvent>, event: LiveEvent) {
+  if let Some(key) = event.key() {
+    pending.retain(|waiting| waiting.key().as_ref() != Some(&key));
+  }
+  pending.push(event);
+}
+
+impl EventSink for Throttle {
+  fn emit(&self, event: &LiveEvent) {
+    if let Some(sender) = &self.sender {
+      // the thread only stops once the sender is dropped
+      let _ = sender.send(event.clone());
+    }
+  }
+}
+
+impl Drop for Throttle {
+  fn drop(&mut self) {
+    self.sender.take();
+    if let Some(handle) = self.handle.take() {
+      let _ = handle.join();
+    }
+  }
+}
+
+/// Import the files of the tracked folder one at a time and tell the sink
+/// about the new hands, the tables and the players whose stats changed
+pub struct LiveImport<S: EventSink> {
+  conn: SqliteConnection,
+  sink: S,
+  registry: Arc<Mutex<TableRegistry>>,
+}
+
+impl<S: EventSink> LiveImport<S> {
+  pub fn new(conn: SqliteConnection, sink: S, registry: Arc<Mutex<TableRegistry>>) -> Self {
+    LiveImport {
+      conn,
+      sink,
+      registry,
+    }
+  }
+
+  pub fn import_file(&mut self, path: &Path) {
+    let file = path.to_string_lossy().to_string();
+    let report = match parse::parse_file_lenient(&file) {
+      Ok(report) => report,
+      Err(e) => return self.error(&file, e.to_string()),
+    };
+    for diagnostic in &report.diagnostics {
+      self.error(&file, diagnostic.to_string());
+    }
+
+    let inserted = match db::insert_new_hands(&mut self.conn, &report.hands) {
+      Ok(inserted) => inserted,
+      Err(e) => return self.error(&file, e.to_string()),
+    };
+    for error in &inserted.errors {
+      self.error(&file, error.to_string());
+    }
+    let mut players: Vec<(&str, bool)> = Vec::new();
+    let now = Instant::now();
+    for hand in inserted.hands {
+      self.seat(hand, now);
+      self.sink.emit(&LiveEvent::HandImported {
+        id: hand.id,
+        table_name: hand.table_name.clone(),
+        hero: hand.hero.as_ref().map(|hero| hero.name.clone()),
+      });
+      for player in hand.players.iter().flatten() {
+        if !players.contains(&(player.name.as_str(), hand.real_money)) {
+          players.push((player.name.as_str(), hand.real_money));
+        }
+      }
+    }
+
+    for (name, money) in players {
+      match db::get_player(&mut self.conn, name, money) {
+        Ok(Some(player)) => self.sink.emit(&LiveEvent::PlayerUpdated { player }),
+        Ok(None) => (),
+        Err(e) => self.error(&file, e.to_string()),
+      }
+    }
+
+    let expired = self.registry.lock().unwrap().expire(now);
+    self.close(expired);
+  }
+
+  // open the table on its first hand and follow the players seated
+  fn seat(&mut self, hand: &HandDetail, now: Instant) {
+    let mut registry = self.registry.lock().unwrap();
+    let (key, update) = registry.update(hand, now);
+    if update == Update::Opened {
+      self.sink.emit(&LiveEvent::TableOpened {
+        table_name: key.clone(),
+      });
+    }
+    if update != Update::Unchanged {
+      let table = registry.get(&key).unwrap();
+      self.sink.emit(&LiveEvent::SeatsChanged {
+        table_name: key,
+        players: table.players.clone(),
+      });
+    }
+  }
+
+  fn close(&self, tables: Vec<String>) {
+    for table_name in tables {
+      self.sink.emit(&LiveEvent::TableClosed { table_name });
+    }
+  }
+
+  fn error(&self, file: &str, message: String) {
+    log::error!("Import of {} failed : {}", file, message);
+    self.sink.emit(&LiveEvent::ImportError {
+      file: file.to_string(),
+      message,
+    });
+  }
+}
+
+// the tables are closed once the tracking stops
+impl<S: EventSink> Drop for LiveImport<S> {
+  fn drop(&mut self) {
+    if let Ok(mut registry) = self.registry.lock() {
+      let tables = registry.close_all();
+      self.close(tables);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct Collect(Arc<Mutex<Vec<LiveEvent>>>);
+
+  impl EventSink for Collect {
+    fn emit(&self, event: &LiveEvent) {
+      self.0.lock().unwrap().push(event.clone());
+    }
+  }
+
+  fn player(name: &str, nb_hand: f32) -> LiveEvent {
+    let mut player = models::Player::new(name);
+    player.nb_hand = nb_hand;
+    LiveEvent::PlayerUpdated { player }
+  }
+
+  #[test]
+  fn throttle_coalesces() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let throttle = Throttle::new(Collect(Arc::clone(&events)), Duration::from_secs(60));
+    // the first event goes at once, the next ones wait for the interval
+    throttle.emit(&player("a", 1.));
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(events.lock().unwrap().len(), 1);
+
+    for nb_hand in 2..100 {
+      throttle.emit(&player("a", nb_hand as f32));
+      throttle.emit(&player("b", nb_hand as f32));
+    }
+    let closed = LiveEvent::TableClosed {
+      table_name: "Ostara III".to_string(),
+    };
+    throttle.emit(&closed);
+    drop(throttle);
+
+    assert_eq!(
+      *events.lock().unwrap(),
+      vec![player("a", 1.), player("a", 99.), player("b", 99.), closed]
+    );
+  }
+
+  #[test]
+  fn renamed_files() {
+    let rename = |from: &str, to: &str| {
+      let event = notify::Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+        .add_path(PathBuf::from(from))
+        .add_path(PathBuf::from(to));
+      changes(&event)
+    };
+    assert_eq!(
+      rename("hands.tmp", "hands.txt"),
+      vec![Change::Written(PathBuf::from("hands.txt"))]
+    );
+    assert_eq!(
+      rename("hands.txt", "hands.bak"),
+      vec![Change::Removed(PathBuf::from("hands.txt"))]
+    );
+    assert_eq!(
+      rename("a.txt", "b.txt"),
+      vec![
+        Change::Removed(PathBuf::from("a.txt")),
+        Change::Written(PathBuf::from("b.txt")),
+      ]
+    );
+  }
+
+  #[test]
+  fn debounce_changes() {
+    let dir = std::env::temp_dir().join(format!("inspector-track-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let written = dir.join("HH20240326 Ostara III.txt");
+    let renamed = dir.join("HH20240326 Ostara IV.txt");
+    std::fs::write(&written, "").unwrap();
+
+    let (sender, receiver) = channel();
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let collect = Arc::clone(&changes);
+    let handle = std::thread::spawn(move || {
+      debounced(receiver, Duration::from_millis(100), |change| {
+        collect.lock().unwrap().push(change)
+      })
+    });
+    // a burst of writes gives a single change
+    for _ in 0..5 {
+      sender.send(Change::Written(written.clone())).unwrap();
     }
-  });
+    // renamed while written, the new name doesn't exist anymore
+    sender.send(Change::Written(renamed.clone())).unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    sender.send(Change::Written(written.clone())).unwrap();
+    sender.send(Change::Removed(written.clone())).unwrap();
+    drop(sender);
+    handle.join().unwrap();
 
-  Ok(())
+    assert_eq!(
+      *changes.lock().unwrap(),
+      vec![
+        Change::Written(written.clone()),
+        Change::Removed(renamed),
+        Change::Removed(written),
+      ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
 }